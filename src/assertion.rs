@@ -0,0 +1,442 @@
+use std::fmt;
+
+use crate::{
+    circuit::{self, Circuit},
+    sim::Sim,
+};
+
+/// Number of shots for distribution assertions, if not specified.
+pub const DEFAULT_SHOTS: usize = 1000;
+/// Significance level for distribution assertions, if not specified.
+pub const DEFAULT_ALPHA: f64 = 0.01;
+/// Tolerance of probability assertions, if not specified.
+pub const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Invalid(String),
+}
+
+impl From<Error> for crate::lines::Error {
+    fn from(e: Error) -> Self {
+        Self::Assertion(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Invalid(source) => write!(
+                f,
+                "Invalid assertion {source:?}: expected `prob REG=VALUE P [±TOL]`, \
+                `class REG ==|!= VALUE` or `dist REG ~ {{VALUE:P, ...}} [p>ALPHA] [shots N]`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Assertion on the state of the simulated circuit, which fails with [`Failure`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    /// Probability of qubits `target` to be measured as `value` is `prob` up to `tol`.
+    Prob {
+        target: String,
+        value: String,
+        prob: f64,
+        tol: f64,
+    },
+    /// Classical bits `target` are (or aren't, if `eq` is false) equal to `value`.
+    Class {
+        target: String,
+        eq: bool,
+        value: String,
+    },
+    /// Measured values of classical bits `target` follow the distribution `dist`
+    /// according to the G-test with significance level `alpha` over `shots` runs.
+    Dist {
+        target: String,
+        dist: Vec<(String, f64)>,
+        alpha: f64,
+        shots: usize,
+    },
+}
+
+/// Failed assertion with the actual value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    assertion: String,
+    actual: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Assertion failed: {} ({})", self.assertion, self.actual)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+impl Assertion {
+    /// Parses the assertion from tokens of the command line, consuming only tokens it consists of.
+    pub fn parse<'a, I: Iterator<Item = &'a str>>(
+        source: &mut std::iter::Peekable<I>,
+    ) -> Result<Self, Error> {
+        let mut tokens = vec![];
+        let next = |source: &mut std::iter::Peekable<I>, tokens: &mut Vec<&'a str>| {
+            let token = source.next();
+            tokens.extend(token);
+            token
+        };
+        let invalid = |tokens: &[&str]| Error::Invalid(tokens.join(" "));
+
+        let assertion = match next(source, &mut tokens) {
+            Some("prob") => {
+                let (target, value) = next(source, &mut tokens)
+                    .and_then(|arg| arg.split_once('='))
+                    .ok_or_else(|| invalid(&tokens))?;
+                let prob = next(source, &mut tokens)
+                    .and_then(|prob| circuit::eval(prob, None).ok())
+                    .ok_or_else(|| invalid(&tokens))?;
+                let tol = match source.next_if(|tol| tol.starts_with(['±', '+'])) {
+                    Some(tol) => {
+                        tokens.push(tol);
+                        let tol = match tol.trim_start_matches(['±', '+', '-']) {
+                            "" => next(source, &mut tokens).ok_or_else(|| invalid(&tokens))?,
+                            tol => tol,
+                        };
+                        tol.parse().map_err(|_| invalid(&tokens))?
+                    }
+                    None => DEFAULT_TOLERANCE,
+                };
+                Assertion::Prob {
+                    target: target.to_string(),
+                    value: value.to_string(),
+                    prob,
+                    tol,
+                }
+            }
+            Some("class") => {
+                let target = next(source, &mut tokens).ok_or_else(|| invalid(&tokens))?;
+                let eq = match next(source, &mut tokens) {
+                    Some("==") => true,
+                    Some("!=") => false,
+                    _ => return Err(invalid(&tokens)),
+                };
+                let value = next(source, &mut tokens).ok_or_else(|| invalid(&tokens))?;
+                Assertion::Class {
+                    target: target.to_string(),
+                    eq,
+                    value: value.to_string(),
+                }
+            }
+            Some("dist") => {
+                let target = next(source, &mut tokens).ok_or_else(|| invalid(&tokens))?;
+                if next(source, &mut tokens) != Some("~") {
+                    return Err(invalid(&tokens));
+                }
+                let mut dist = String::new();
+                while !dist.ends_with('}') {
+                    dist += next(source, &mut tokens).ok_or_else(|| invalid(&tokens))?;
+                }
+                let dist = dist
+                    .strip_prefix('{')
+                    .and_then(|dist| dist.strip_suffix('}'))
+                    .ok_or_else(|| invalid(&tokens))?
+                    .split(',')
+                    .map(|entry| {
+                        let (value, prob) = entry.split_once(':')?;
+                        Some((value.to_string(), circuit::eval(prob, None).ok()?))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|dist| {
+                        let total: f64 = dist.iter().map(|(_, prob)| prob).sum();
+                        dist.iter().all(|(_, prob)| *prob >= 0.0) && (total - 1.0).abs() < 1e-6
+                    })
+                    .ok_or_else(|| invalid(&tokens))?;
+
+                let mut alpha = DEFAULT_ALPHA;
+                let mut shots = DEFAULT_SHOTS;
+                loop {
+                    if let Some(arg) = source.next_if(|arg| arg.starts_with("p>")) {
+                        tokens.push(arg);
+                        alpha = arg[2..].parse().map_err(|_| invalid(&tokens))?;
+                    } else if source.next_if_eq(&"shots").is_some() {
+                        tokens.push("shots");
+                        shots = next(source, &mut tokens)
+                            .and_then(|shots| shots.parse().ok())
+                            .ok_or_else(|| invalid(&tokens))?;
+                    } else {
+                        break;
+                    }
+                }
+                Assertion::Dist {
+                    target: target.to_string(),
+                    dist,
+                    alpha,
+                    shots,
+                }
+            }
+            _ => return Err(invalid(&tokens)),
+        };
+        Ok(assertion)
+    }
+
+    /// Checks the assertion against the current state of simulation of the circuit,
+    /// returning the failure, if it doesn't hold. Distribution assertions re-run the circuit.
+    pub fn check(&self, circuit: &Circuit, sim: &mut Sim) -> circuit::Result<Option<Failure>> {
+        let value_of = |target: &str, value: &str, mask: usize| {
+            circuit::parse_value(value, mask.count_ones() as usize)
+                .ok_or_else(|| circuit::Error::InvalidValue(target.to_string(), value.to_string()))
+        };
+        let failure = |actual: String| {
+            Some(Failure {
+                assertion: self.to_string(),
+                actual,
+            })
+        };
+
+        match self {
+            Assertion::Prob {
+                target,
+                value,
+                prob,
+                tol,
+            } => {
+                let mask = circuit.q_mask_of(target)?;
+                let value = value_of(target, value, mask)?;
+                let actual: f64 = sim
+                    .get_probabilities()
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| circuit::gather(*idx, mask) == value)
+                    .map(|(_, prob)| prob)
+                    .sum();
+                match (actual - prob).abs() <= *tol {
+                    true => Ok(None),
+                    false => Ok(failure(format!("actual {actual:.6}"))),
+                }
+            }
+            Assertion::Class { target, eq, value } => {
+                let mask = circuit.c_mask_of(target)?;
+                let value = value_of(target, value, mask)?;
+                let actual = circuit::gather(sim.get_class(), mask);
+                match (actual == value) == *eq {
+                    true => Ok(None),
+                    false => Ok(failure(format!("actual {actual}"))),
+                }
+            }
+            Assertion::Dist {
+                target,
+                dist,
+                alpha,
+                shots,
+            } => {
+                let mask = circuit.c_mask_of(target)?;
+                let expected = dist
+                    .iter()
+                    .map(|(value, prob)| Ok((value_of(target, value, mask)?, *prob)))
+                    .collect::<circuit::Result<Vec<_>>>()?;
+                let mut observed = vec![];
+                for (class, count) in sim.sample_aside(*shots).iter() {
+                    let value = circuit::gather(class, mask);
+                    match observed.iter_mut().find(|(other, _)| *other == value) {
+                        Some((_, total)) => *total += count,
+                        None => observed.push((value, count)),
+                    }
+                }
+                let p = g_test(&expected, &observed);
+                match p > *alpha {
+                    true => Ok(None),
+                    false => Ok(failure(format!("G-test p = {p:.4} over {shots} shots"))),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::Prob {
+                target,
+                value,
+                prob,
+                tol,
+            } => write!(f, "prob {target}={value} {prob} ±{tol}"),
+            Assertion::Class { target, eq, value } => {
+                let op = if *eq { "==" } else { "!=" };
+                write!(f, "class {target} {op} {value}")
+            }
+            Assertion::Dist {
+                target,
+                dist,
+                alpha,
+                shots,
+            } => {
+                let dist: Vec<_> = dist
+                    .iter()
+                    .map(|(value, prob)| format!("{value}:{prob}"))
+                    .collect();
+                write!(
+                    f,
+                    "dist {target} ~ {{{}}} p>{alpha} shots {shots}",
+                    dist.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// P-value of the G-test of `observed` counts against `expected` probabilities of values.
+/// Values, which are observed but not expected, reject the hypothesis at once.
+fn g_test(expected: &[(usize, f64)], observed: &[(usize, usize)]) -> f64 {
+    let shots: usize = observed.iter().map(|(_, count)| count).sum();
+    let mut g = 0.0;
+    for (value, count) in observed {
+        let prob: f64 = expected
+            .iter()
+            .filter(|(other, _)| other == value)
+            .map(|(_, prob)| prob)
+            .sum();
+        if prob == 0.0 {
+            return 0.0;
+        }
+        let count = *count as f64;
+        g += 2.0 * count * (count / (prob * shots as f64)).ln();
+    }
+
+    let mut values: Vec<_> = expected
+        .iter()
+        .filter(|(_, prob)| *prob > 0.0)
+        .map(|(value, _)| value)
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+    match values.len() {
+        0 | 1 => 1.0,
+        categories => chi2_survival((categories - 1) as f64, g.max(0.0)),
+    }
+}
+
+/// Probability of chi-square distributed value with `df` degrees of freedom to exceed `x`,
+/// which is the regularized upper incomplete gamma function `Q(df / 2, x / 2)`.
+fn chi2_survival(df: f64, x: f64) -> f64 {
+    let (a, x) = (df / 2.0, x / 2.0);
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefix = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // series of the lower function
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        while term.abs() > sum.abs() * 1e-15 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+        }
+        (1.0 - sum * prefix).max(0.0)
+    } else {
+        // continued fraction by the modified Lentz's method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let (mut c, mut d) = (1.0 / tiny, 1.0 / b);
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            d = if d.abs() < tiny { tiny } else { d };
+            c = b + an / c;
+            c = if c.abs() < tiny { tiny } else { c };
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        prefix * h
+    }
+}
+
+/// Logarithm of the gamma function by the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |acc, (idx, coef)| {
+            acc + coef / (x + 1.0 + idx as f64)
+        });
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Assertion, Error> {
+        Assertion::parse(&mut source.split_whitespace().peekable())
+    }
+
+    #[test]
+    fn parse_assertions() {
+        assert_eq!(
+            parse("prob q=00 0.5 ±0.01"),
+            Ok(Assertion::Prob {
+                target: "q".to_string(),
+                value: "00".to_string(),
+                prob: 0.5,
+                tol: 0.01,
+            })
+        );
+        assert_eq!(
+            parse("prob q[1]=1 1/2 +- 0.1").map(|assertion| assertion.to_string()),
+            Ok("prob q[1]=1 0.5 ±0.1".to_string())
+        );
+        assert_eq!(
+            parse("class c != 3"),
+            Ok(Assertion::Class {
+                target: "c".to_string(),
+                eq: false,
+                value: "3".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("dist c ~ {00:0.5, 11:0.5} p>0.05"),
+            Ok(Assertion::Dist {
+                target: "c".to_string(),
+                dist: vec![("00".to_string(), 0.5), ("11".to_string(), 0.5)],
+                alpha: 0.05,
+                shots: DEFAULT_SHOTS,
+            })
+        );
+        assert_eq!(
+            parse("dist c ~ {0:0.6, 1:0.6}"),
+            Err(Error::Invalid("dist c ~ {0:0.6, 1:0.6}".to_string()))
+        );
+    }
+
+    #[test]
+    fn g_test_p_values() {
+        assert!((chi2_survival(1.0, 3.841459) - 0.05).abs() < 1e-6);
+        assert!((chi2_survival(2.0, 5.991465) - 0.05).abs() < 1e-6);
+        assert!((chi2_survival(4.0, 1.0) - 0.909796).abs() < 1e-6);
+
+        let expected = [(0, 0.5), (3, 0.5)];
+        assert!(g_test(&expected, &[(0, 510), (3, 490)]) > 0.5);
+        assert!(g_test(&expected, &[(0, 600), (3, 400)]) < 1e-6);
+        assert_eq!(g_test(&expected, &[(0, 500), (1, 1), (3, 499)]), 0.0);
+    }
+}
@@ -0,0 +1,91 @@
+use qvnt::prelude::{op, Applicable, MultiOp};
+
+fn fold_regs(regs: &[usize]) -> usize {
+    regs.iter().fold(0, |acc, reg| acc | reg)
+}
+
+fn any(regs: &[usize], args: &[f64], op: fn(usize) -> MultiOp) -> Option<MultiOp> {
+    let regs = fold_regs(regs);
+    (regs != 0 && args.is_empty()).then(|| op(regs))
+}
+
+fn exact(regs: &[usize], args: &[f64], num: u32, op: fn(usize) -> MultiOp) -> Option<MultiOp> {
+    let regs = fold_regs(regs);
+    (regs.count_ones() == num && args.is_empty()).then(|| op(regs))
+}
+
+fn rotate(
+    regs: &[usize],
+    args: &[f64],
+    num: u32,
+    op: fn(f64, usize) -> MultiOp,
+) -> Option<MultiOp> {
+    let regs = fold_regs(regs);
+    (regs.count_ones() == num && args.len() == 1).then(|| op(args[0], regs))
+}
+
+/// Builds the operator for a built-in gate, mirroring the gate set of `qvnt::qasm::Int`.
+///
+/// Returns `None` for unknown gates or when registers/arguments do not fit the gate.
+pub fn process(name: &str, regs: &[usize], args: &[f64]) -> Option<MultiOp> {
+    match name {
+        s if s.len() > 1 && s.starts_with(['c', 'C']) => {
+            let (&ctrl, regs) = regs.split_first()?;
+            process(&name[1..], regs, args)?.c(ctrl)
+        }
+        "x" | "X" => any(regs, args, op::x),
+        "y" | "Y" => any(regs, args, op::y),
+        "z" | "Z" => any(regs, args, op::z),
+        "s" | "S" => any(regs, args, op::s),
+        // `qvnt` 0.4 applies plain `s` and `t` for `sdg` and `tdg`, here they are inverted
+        "sdg" | "SDG" => any(regs, args, |a| op::s(a).dgr()),
+        "t" | "T" => any(regs, args, op::t),
+        "tdg" | "TDG" => any(regs, args, |a| op::t(a).dgr()),
+
+        "h" | "H" => any(regs, args, op::h),
+        "qft" | "QFT" => any(regs, args, op::qft),
+
+        "rx" | "RX" => rotate(regs, args, 1, op::rx),
+        "ry" | "RY" => rotate(regs, args, 1, op::ry),
+        "rz" | "RZ" => rotate(regs, args, 1, op::rz),
+
+        "rxx" | "RXX" => rotate(regs, args, 2, op::rxx),
+        "ryy" | "RYY" => rotate(regs, args, 2, op::ryy),
+        "rzz" | "RZZ" => rotate(regs, args, 2, op::rzz),
+
+        "swap" | "SWAP" => exact(regs, args, 2, op::swap),
+        "sqrt_swap" | "SQRT_SWAP" => exact(regs, args, 2, op::sqrt_swap),
+        "i_swap" | "I_SWAP" => exact(regs, args, 2, op::i_swap),
+        "sqrt_i_swap" | "SQRT_I_SWAP" => exact(regs, args, 2, op::sqrt_i_swap),
+
+        "u1" | "U1" => rotate(regs, args, 1, op::u1),
+        "u2" | "U2" => {
+            let regs = fold_regs(regs);
+            (regs.count_ones() == 1 && args.len() == 2).then(|| op::u2(args[0], args[1], regs))
+        }
+        "u3" | "U3" => {
+            let regs = fold_regs(regs);
+            (regs.count_ones() == 1 && args.len() == 3)
+                .then(|| op::u3(args[0], args[1], args[2], regs))
+        }
+
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_gates() {
+        assert_eq!(process("x", &[0b111], &[]), Some(op::x(0b111)));
+        assert_eq!(
+            process("ccx", &[0b100, 0b010, 0b001], &[]),
+            op::x(0b001).c(0b110)
+        );
+        assert_eq!(process("rx", &[0b101], &[1.0]), None);
+        assert_eq!(process("swap", &[0b001], &[]), None);
+        assert_eq!(process("foo", &[0b001], &[]), None);
+    }
+}
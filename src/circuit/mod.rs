@@ -0,0 +1,867 @@
+use std::{collections::HashMap, fmt};
+
+use num_complex::Complex64 as C;
+use qasm::{Argument, AstNode};
+use qvnt::{
+    prelude::{Applicable, MultiOp},
+    qasm::Ast,
+};
+
+pub mod gates;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    UnknownReg(String),
+    IdxOutOfRange(String, usize),
+    UnknownGate(String),
+    UnevaluatedArgument(String, meval::Error),
+    NonUnitary(String),
+    UnmatchedQubits(usize, usize),
+    TooManyBits(String, usize),
+    InvalidInit(String, String),
+    InvalidValue(String, String),
+    UnnamedOperand(usize),
+    QubitOutOfRange(usize, usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownReg(name) => write!(f, "There's no register called {name:?}"),
+            Error::IdxOutOfRange(name, idx) => {
+                write!(
+                    f,
+                    "Index (={idx}) is out of bounds for register: {name}[{idx}]"
+                )
+            }
+            Error::UnknownGate(name) => {
+                write!(f, "Gate {name:?} cannot be applied to given operands")
+            }
+            Error::UnevaluatedArgument(arg, err) => {
+                write!(f, "Cannot evaluate gate argument [{arg}]: {err:?}")
+            }
+            Error::NonUnitary(op) => {
+                write!(f, "Operation {op} makes the circuit non-unitary")
+            }
+            Error::UnmatchedQubits(lhs, rhs) => {
+                write!(
+                    f,
+                    "Circuits act on different number of qubits: {lhs} and {rhs}"
+                )
+            }
+            Error::TooManyBits(name, num) => {
+                write!(
+                    f,
+                    "Register {name} makes {num} bits in total, but at most {} are supported",
+                    usize::BITS
+                )
+            }
+            Error::InvalidInit(name, value) => {
+                write!(
+                    f,
+                    "Register {name} cannot be initialised with {value:?}: expected bit string, decimal integer, `+` or `-`"
+                )
+            }
+            Error::InvalidValue(name, value) => {
+                write!(f, "Value {value:?} doesn't fit {name}")
+            }
+            Error::QubitOutOfRange(idx, q_num) => {
+                write!(f, "Qubit {idx} is out of range of {q_num} qubits")
+            }
+            Error::UnnamedOperand(mask) => {
+                write!(
+                    f,
+                    "Operand {mask:#b} is neither a register nor a single bit and cannot be written as QASM"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T = ()> = std::result::Result<T, Error>;
+
+thread_local! {
+    static CONTEXT: meval::Context<'static> = {
+        let mut ctx = meval::Context::empty();
+        ctx.var("pi", std::f64::consts::PI);
+
+        ctx.func("sqrt", f64::sqrt);
+        ctx.func("exp", f64::exp);
+        ctx.func("ln", f64::ln);
+        ctx.func("abs", f64::abs);
+
+        ctx.func("floor", f64::floor);
+        ctx.func("ceil", f64::ceil);
+        ctx.func("round", f64::round);
+
+        ctx.func2("atan2", f64::atan2);
+        ctx.funcn("max", |xs| xs.iter().cloned().fold(f64::NEG_INFINITY, f64::max), 1..);
+        ctx.funcn("min", |xs| xs.iter().cloned().fold(f64::INFINITY, f64::min), 1..);
+
+        ctx
+    }
+}
+
+/// Evaluates gate argument in the same context, as `qvnt` does.
+pub fn eval<'a, V: IntoIterator<Item = (&'a str, f64)>>(expr: &str, vars: V) -> Result<f64> {
+    let mut ctx = CONTEXT.with(Clone::clone);
+    for (var, value) in vars {
+        ctx.var(var, value);
+    }
+
+    expr.parse::<meval::Expr>()
+        .and_then(|expr| expr.eval_with_context(ctx))
+        .map_err(|err| Error::UnevaluatedArgument(expr.to_string(), err))
+}
+
+/// Represents `arg` as a simple non-zero fraction of `pi`: numerator and denominator.
+fn pi_fraction(arg: f64) -> Option<(i64, i64)> {
+    (1..=8_i64).find_map(|den| {
+        let num = arg * den as f64 / std::f64::consts::PI;
+        (num.round() != 0.0 && (num - num.round()).abs() < 1e-9).then(|| (num.round() as i64, den))
+    })
+}
+
+/// Formats gate argument, writing simple fractions of `pi` symbolically.
+pub fn fmt_arg(arg: f64) -> String {
+    if let Some((num, den)) = pi_fraction(arg) {
+        let num = match num {
+            1 => String::new(),
+            -1 => "-".to_string(),
+            num => num.to_string(),
+        };
+        return match den {
+            1 => format!("{num}pi"),
+            den => format!("{num}pi/{den}"),
+        };
+    }
+
+    let arg = format!("{:.4}", arg);
+    arg.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Formats gate argument as QASM expression, which evaluates back to the same value
+/// (up to rounding of simple fractions of `pi`).
+pub fn qasm_arg(arg: f64) -> String {
+    match pi_fraction(arg) {
+        Some((num, den)) => {
+            let num = match num {
+                1 => "pi".to_string(),
+                -1 => "-pi".to_string(),
+                num => format!("{num}*pi"),
+            };
+            match den {
+                1 => num,
+                den => format!("{num}/{den}"),
+            }
+        }
+        None => arg.to_string(),
+    }
+}
+
+/// Formats gate application as `name(arg0,arg1,...)`.
+pub fn label(name: &str, args: &[f64]) -> String {
+    if args.is_empty() {
+        name.to_string()
+    } else {
+        let args: Vec<_> = args.iter().cloned().map(fmt_arg).collect();
+        format!("{}({})", name, args.join(","))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub name: String,
+    pub size: usize,
+    pub offset: usize,
+}
+
+impl Register {
+    pub fn mask(&self) -> usize {
+        usize::MAX
+            .checked_shr(usize::BITS.saturating_sub(self.size as u32))
+            .and_then(|ones| ones.checked_shl(self.offset as u32))
+            .unwrap_or(0)
+    }
+
+    /// Creates register of `size` bits from `offset`, if its bits still fit into `usize` mask.
+    pub fn new(name: &str, size: usize, offset: usize) -> Result<Self> {
+        match offset.checked_add(size) {
+            Some(num) if num <= usize::BITS as usize => Ok(Self {
+                name: name.to_string(),
+                size,
+                offset,
+            }),
+            _ => Err(Error::TooManyBits(
+                name.to_string(),
+                offset.saturating_add(size),
+            )),
+        }
+    }
+
+    /// Returns the name of the register, which consists of exactly `mask` bits.
+    pub fn name_of(regs: &[Self], mask: usize) -> Option<&str> {
+        regs.iter()
+            .find(|reg| reg.mask() == mask)
+            .map(|reg| reg.name.as_str())
+    }
+
+    /// Returns the name of the register's bit, which has global index `idx`.
+    pub fn bit_name(regs: &[Self], idx: usize) -> Option<String> {
+        regs.iter()
+            .find(|reg| (reg.offset..reg.offset + reg.size).contains(&idx))
+            .map(|reg| format!("{}[{}]", reg.name, idx - reg.offset))
+    }
+}
+
+/// Iterates over indices of set bits of `mask` in ascending order.
+pub fn bits(mask: usize) -> impl Iterator<Item = usize> + Clone {
+    (0..usize::BITS as usize).filter(move |idx| mask & (1 << idx) != 0)
+}
+
+/// Collects bits of `value` at positions of `mask` into an integer, the lowest bit first.
+pub fn gather(value: usize, mask: usize) -> usize {
+    bits(mask)
+        .enumerate()
+        .fold(0, |acc, (idx, bit)| acc | ((value >> bit) & 1) << idx)
+}
+
+/// Parses value of `size` bits: a bit string with the most significant bit first
+/// (of exactly `size` digits or prefixed with `0b`), or a decimal integer.
+pub fn parse_value(value: &str, size: usize) -> Option<usize> {
+    let bit_string = value.strip_prefix("0b").or_else(|| {
+        (value.len() == size && value.chars().all(|c| c == '0' || c == '1')).then_some(value)
+    });
+    let value = match bit_string {
+        Some(bit_string) => usize::from_str_radix(bit_string, 2),
+        None => value.parse(),
+    }
+    .ok()?;
+    (value >> size == 0).then_some(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub regs: Vec<String>,
+    pub args: Vec<String>,
+    pub body: Vec<(String, Vec<String>, Vec<String>)>,
+}
+
+impl Macro {
+    /// Writes the definition of the macro as QASM `gate` statement.
+    pub fn to_qasm(&self, name: &str) -> String {
+        let args = if self.args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", self.args.join(", "))
+        };
+        let body: String = self
+            .body
+            .iter()
+            .map(|(name, regs, args)| match args.len() {
+                0 => format!(" {} {};", name, regs.join(", ")),
+                _ => format!(" {}({}) {};", name, args.join(", "), regs.join(", ")),
+            })
+            .collect();
+        format!("gate {name}{args} {} {{{body} }}\n", self.regs.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gate {
+    pub name: String,
+    pub regs: Vec<usize>,
+    pub args: Vec<f64>,
+}
+
+impl Gate {
+    pub fn act_on(&self) -> usize {
+        self.regs.iter().fold(0, |acc, reg| acc | reg)
+    }
+
+    /// Splits built-in gate on the number of control registers and the name of controlled gate,
+    /// e.g. `ccx` is `x` with 2 control registers.
+    pub fn controls(&self) -> (usize, &str) {
+        let mut name = self.name.as_str();
+        let mut ctrl = 0;
+        while name.len() > 1 && name.starts_with(['c', 'C']) && ctrl < self.regs.len() {
+            name = &name[1..];
+            ctrl += 1;
+        }
+        (ctrl, name)
+    }
+}
+
+/// Single instruction of the lowered circuit.
+///
+/// Qubits and bits are stored as masks, the same way `qvnt` addresses them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Gate(Gate),
+    Macro(Gate, Vec<Op>),
+    Measure(usize, usize),
+    Reset(usize),
+    Barrier(usize),
+    If(usize, usize, Box<Op>),
+}
+
+impl Op {
+    pub fn name(&self) -> String {
+        match self {
+            Op::Gate(gate) | Op::Macro(gate, _) => gate.name.clone(),
+            Op::Measure(..) => "measure".to_string(),
+            Op::Reset(_) => "reset".to_string(),
+            Op::Barrier(_) => "barrier".to_string(),
+            Op::If(_, _, op) => format!("if({})", op.name()),
+        }
+    }
+
+    pub fn act_on(&self) -> usize {
+        match self {
+            Op::Gate(gate) | Op::Macro(gate, _) => gate.act_on(),
+            Op::Measure(q, _) | Op::Reset(q) | Op::Barrier(q) => *q,
+            Op::If(_, _, op) => op.act_on(),
+        }
+    }
+
+    /// Operator of unitary instruction. Returns `None` for measurements, resets and conditions.
+    pub fn unitary(&self) -> Option<MultiOp> {
+        match self {
+            Op::Gate(gate) => gates::process(&gate.name, &gate.regs, &gate.args),
+            Op::Macro(_, ops) => ops
+                .iter()
+                .try_fold(MultiOp::default(), |acc, op| Some(acc * op.unitary()?)),
+            Op::Barrier(_) => Some(MultiOp::default()),
+            Op::Measure(..) | Op::Reset(_) | Op::If(..) => None,
+        }
+    }
+}
+
+/// Flat representation of the program, lowered from the sequence of [`Ast`]s.
+///
+/// Unlike [`qvnt::qasm::Int`], it keeps every gate application as a separate instruction,
+/// so it can be inspected, transformed and re-simulated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Circuit {
+    pub q_regs: Vec<Register>,
+    pub c_regs: Vec<Register>,
+    pub macros: HashMap<String, Macro>,
+    pub ops: Vec<Op>,
+}
+
+impl Circuit {
+    #[cfg(test)]
+    pub fn from_asts<'a, 't: 'a>(asts: impl IntoIterator<Item = &'a Ast<'t>>) -> Result<Self> {
+        Self::from_asts_with(asts, &[])
+    }
+
+    /// Lowers ASTs, evaluating gate arguments with parameters `vars` bound.
+    pub fn from_asts_with<'a, 't: 'a>(
+        asts: impl IntoIterator<Item = &'a Ast<'t>>,
+        vars: &[(String, f64)],
+    ) -> Result<Self> {
+        let mut circuit = Self::default();
+        for ast in asts {
+            for node in ast.clone() {
+                circuit.add_node(node, vars)?;
+            }
+        }
+        Ok(circuit)
+    }
+
+    pub fn q_num(&self) -> usize {
+        self.q_regs.iter().map(|reg| reg.size).sum()
+    }
+
+    pub fn c_num(&self) -> usize {
+        self.c_regs.iter().map(|reg| reg.size).sum()
+    }
+
+    fn add_node(&mut self, node: AstNode<'_>, vars: &[(String, f64)]) -> Result {
+        match node {
+            AstNode::QReg(name, size) => {
+                let reg = Register::new(name, size as usize, self.q_num())?;
+                self.q_regs.push(reg);
+            }
+            AstNode::CReg(name, size) => {
+                let reg = Register::new(name, size as usize, self.c_num())?;
+                self.c_regs.push(reg);
+            }
+            AstNode::Gate(name, regs, args, nodes) => {
+                let body = nodes
+                    .into_iter()
+                    .filter_map(|node| match node {
+                        AstNode::ApplyGate(name, regs, args) => Some((
+                            name.to_string(),
+                            regs.into_iter()
+                                .map(|reg| match reg {
+                                    Argument::Qubit(name, _) | Argument::Register(name) => {
+                                        name.to_string()
+                                    }
+                                })
+                                .collect(),
+                            args.into_iter().map(str::to_string).collect(),
+                        )),
+                        _ => None,
+                    })
+                    .collect();
+                let macro_def = Macro {
+                    regs: regs.into_iter().map(str::to_string).collect(),
+                    args: args.into_iter().map(str::to_string).collect(),
+                    body,
+                };
+                self.macros.insert(name.to_string(), macro_def);
+            }
+            node => {
+                if let Some(op) = self.lower(node, vars)? {
+                    self.ops.push(op);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lower(&self, node: AstNode<'_>, vars: &[(String, f64)]) -> Result<Option<Op>> {
+        Ok(Some(match node {
+            AstNode::Barrier(reg) => Op::Barrier(self.q_mask(reg)?),
+            AstNode::Reset(reg) => Op::Reset(self.q_mask(reg)?),
+            AstNode::Measure(q_reg, c_reg) => Op::Measure(self.q_mask(q_reg)?, self.c_mask(c_reg)?),
+            AstNode::ApplyGate(name, regs, args) => {
+                let regs = regs
+                    .into_iter()
+                    .map(|reg| self.q_mask(reg))
+                    .collect::<Result<Vec<_>>>()?;
+                let args = args
+                    .into_iter()
+                    .map(|arg| eval(arg, vars.iter().map(|(var, val)| (var.as_str(), *val))))
+                    .collect::<Result<Vec<_>>>()?;
+                self.apply(name, regs, args)?
+            }
+            AstNode::If(c_reg, val, node) => match self.lower(*node, vars)? {
+                Some(op) => Op::If(
+                    self.c_mask(Argument::Register(c_reg))?,
+                    val as usize,
+                    Box::new(op),
+                ),
+                None => return Ok(None),
+            },
+            AstNode::QReg(..) | AstNode::CReg(..) | AstNode::Gate(..) | AstNode::Opaque(..) => {
+                return Ok(None)
+            }
+        }))
+    }
+
+    fn apply(&self, name: &str, regs: Vec<usize>, args: Vec<f64>) -> Result<Op> {
+        let gate = Gate {
+            name: name.to_string(),
+            regs,
+            args,
+        };
+
+        match self.macros.get(name) {
+            Some(macro_def) => {
+                if macro_def.regs.len() != gate.regs.len()
+                    || macro_def.args.len() != gate.args.len()
+                {
+                    return Err(Error::UnknownGate(gate.name));
+                }
+
+                let regs: HashMap<&str, usize> = macro_def
+                    .regs
+                    .iter()
+                    .map(String::as_str)
+                    .zip(gate.regs.iter().cloned())
+                    .collect();
+                let args: Vec<(&str, f64)> = macro_def
+                    .args
+                    .iter()
+                    .map(String::as_str)
+                    .zip(gate.args.iter().cloned())
+                    .collect();
+
+                let ops = macro_def
+                    .body
+                    .iter()
+                    .map(|(name_i, regs_i, args_i)| {
+                        let regs_i = regs_i
+                            .iter()
+                            .map(|reg| {
+                                regs.get(reg.as_str())
+                                    .cloned()
+                                    .ok_or_else(|| Error::UnknownReg(reg.clone()))
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        let args_i = args_i
+                            .iter()
+                            .map(|arg| eval(arg, args.iter().cloned()))
+                            .collect::<Result<Vec<_>>>()?;
+                        if name_i == name {
+                            return Err(Error::UnknownGate(name_i.clone()));
+                        }
+                        self.apply(name_i, regs_i, args_i)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(Op::Macro(gate, ops))
+            }
+            None => match gates::process(name, &gate.regs, &gate.args) {
+                Some(_) => Ok(Op::Gate(gate)),
+                None => Err(Error::UnknownGate(gate.name)),
+            },
+        }
+    }
+
+    fn find_reg<'a>(
+        regs: &'a [Register],
+        arg: &Argument<'_>,
+    ) -> Result<(&'a Register, Option<usize>)> {
+        let (name, idx) = match *arg {
+            Argument::Qubit(name, idx) => (name, Some(idx as usize)),
+            Argument::Register(name) => (name, None),
+        };
+        regs.iter()
+            .find(|reg| reg.name == name)
+            .map(|reg| (reg, idx))
+            .ok_or_else(|| Error::UnknownReg(name.to_string()))
+    }
+
+    fn mask(regs: &[Register], arg: Argument<'_>) -> Result<usize> {
+        match Self::find_reg(regs, &arg)? {
+            (reg, None) => Ok(reg.mask()),
+            (reg, Some(idx)) if idx < reg.size => 1_usize
+                .checked_shl((reg.offset + idx) as u32)
+                .ok_or_else(|| Error::IdxOutOfRange(reg.name.clone(), idx)),
+            (reg, Some(idx)) => Err(Error::IdxOutOfRange(reg.name.clone(), idx)),
+        }
+    }
+
+    pub fn q_mask(&self, arg: Argument<'_>) -> Result<usize> {
+        Self::mask(&self.q_regs, arg)
+    }
+
+    /// Mask of qubits, written as `q[0]` for a single qubit or `q` for the whole register.
+    pub fn q_mask_of(&self, arg: &str) -> Result<usize> {
+        self.q_mask(Self::parse_arg(arg)?)
+    }
+
+    pub fn c_mask(&self, arg: Argument<'_>) -> Result<usize> {
+        Self::mask(&self.c_regs, arg)
+    }
+
+    /// Mask of bits, written as `c[0]` for a single bit or `c` for the whole register.
+    pub fn c_mask_of(&self, arg: &str) -> Result<usize> {
+        self.c_mask(Self::parse_arg(arg)?)
+    }
+
+    fn parse_arg(arg: &str) -> Result<Argument<'_>> {
+        match arg.strip_suffix(']').and_then(|arg| arg.split_once('[')) {
+            Some((name, idx)) => Ok(Argument::Qubit(
+                name,
+                idx.parse()
+                    .map_err(|_| Error::UnknownReg(arg.to_string()))?,
+            )),
+            None => Ok(Argument::Register(arg)),
+        }
+    }
+
+    /// Builds QASM source, which sets quantum register `name` to `value`:
+    /// a bit string (with the most significant qubit first), a decimal integer, `+` or `-`.
+    ///
+    /// If the register was already used, it is reset beforehand.
+    pub fn init_source(&self, name: &str, value: &str) -> Result<String> {
+        let (reg, _) = Self::find_reg(&self.q_regs, &Argument::Register(name))?;
+        let invalid = || Error::InvalidInit(name.to_string(), value.to_string());
+
+        let mut source = String::new();
+        if self.ops.iter().any(|op| op.act_on() & reg.mask() != 0) {
+            source += &format!("reset {name};");
+        }
+        match value {
+            "+" => source += &format!("h {name};"),
+            "-" => source += &format!("x {name};h {name};"),
+            value => {
+                let value = parse_value(value, reg.size).ok_or_else(invalid)?;
+                if value == (1 << reg.size) - 1 {
+                    source += &format!("x {name};");
+                } else {
+                    for idx in bits(value) {
+                        source += &format!("x {name}[{idx}];");
+                    }
+                }
+            }
+        }
+        Ok(source)
+    }
+
+    /// Expands applications of macros into the gates they consist of.
+    pub fn flatten(&self) -> Self {
+        fn flatten_op(op: &Op, ops: &mut Vec<Op>) {
+            match op {
+                Op::Macro(_, body) => body.iter().for_each(|op| flatten_op(op, ops)),
+                Op::If(c_mask, val, op) => {
+                    let mut body = vec![];
+                    flatten_op(op, &mut body);
+                    ops.extend(
+                        body.into_iter()
+                            .map(|op| Op::If(*c_mask, *val, Box::new(op))),
+                    );
+                }
+                op => ops.push(op.clone()),
+            }
+        }
+
+        let mut ops = vec![];
+        self.ops.iter().for_each(|op| flatten_op(op, &mut ops));
+        Self {
+            q_regs: self.q_regs.clone(),
+            c_regs: self.c_regs.clone(),
+            macros: HashMap::new(),
+            ops,
+        }
+    }
+
+    /// Writes the circuit as OpenQASM source, where applied macros are defined as gates.
+    pub fn to_qasm(&self) -> Result<String> {
+        let mut source = "OPENQASM 2.0;\n".to_string();
+        let mut defined = vec![];
+        for op in &self.ops {
+            self.write_macros(op, &mut defined, &mut source);
+        }
+        for reg in &self.q_regs {
+            source += &format!("qreg {}[{}];\n", reg.name, reg.size);
+        }
+        for reg in &self.c_regs {
+            source += &format!("creg {}[{}];\n", reg.name, reg.size);
+        }
+        for op in &self.ops {
+            self.write_op(op, &mut source)?;
+        }
+        Ok(source)
+    }
+
+    /// Writes definitions of macros, applied by `op`, which are not `defined` yet.
+    fn write_macros<'a>(&'a self, op: &'a Op, defined: &mut Vec<&'a str>, source: &mut String) {
+        match op {
+            Op::Macro(gate, body) => {
+                for op in body {
+                    self.write_macros(op, defined, source);
+                }
+                if let Some(macro_def) = self.macros.get(&gate.name) {
+                    if !defined.contains(&gate.name.as_str()) {
+                        defined.push(&gate.name);
+                        *source += &macro_def.to_qasm(&gate.name);
+                    }
+                }
+            }
+            Op::If(_, _, op) => self.write_macros(op, defined, source),
+            _ => {}
+        }
+    }
+
+    /// Writes a single instruction as QASM statement.
+    pub fn op_to_qasm(&self, op: &Op) -> Result<String> {
+        let mut source = String::new();
+        self.write_op(op, &mut source)?;
+        Ok(source.trim_end().replace('\n', " "))
+    }
+
+    fn write_op(&self, op: &Op, source: &mut String) -> Result {
+        let operand = |regs: &[Register], mask: usize| {
+            Register::name_of(regs, mask)
+                .map(str::to_string)
+                .or_else(|| match mask.count_ones() {
+                    1 => Register::bit_name(regs, mask.trailing_zeros() as usize),
+                    _ => None,
+                })
+                .ok_or(Error::UnnamedOperand(mask))
+        };
+
+        match op {
+            Op::Gate(gate) => {
+                let regs = gate
+                    .regs
+                    .iter()
+                    .map(|&mask| operand(&self.q_regs, mask))
+                    .collect::<Result<Vec<_>>>()?;
+                let name = if gate.args.is_empty() {
+                    gate.name.clone()
+                } else {
+                    let args: Vec<_> = gate.args.iter().cloned().map(qasm_arg).collect();
+                    format!("{}({})", gate.name, args.join(", "))
+                };
+                *source += &format!("{} {};\n", name, regs.join(", "));
+            }
+            Op::Macro(gate, _) if self.macros.contains_key(&gate.name) => {
+                self.write_op(&Op::Gate(gate.clone()), source)?;
+            }
+            Op::Macro(_, ops) => {
+                for op in ops {
+                    self.write_op(op, source)?;
+                }
+            }
+            Op::Measure(q_mask, c_mask) => {
+                *source += &format!(
+                    "measure {} -> {};\n",
+                    operand(&self.q_regs, *q_mask)?,
+                    operand(&self.c_regs, *c_mask)?
+                );
+            }
+            Op::Reset(q_mask) => {
+                *source += &format!("reset {};\n", operand(&self.q_regs, *q_mask)?)
+            }
+            Op::Barrier(q_mask) => {
+                *source += &format!("barrier {};\n", operand(&self.q_regs, *q_mask)?)
+            }
+            Op::If(c_mask, val, op) => {
+                let c_reg = Register::name_of(&self.c_regs, *c_mask)
+                    .ok_or(Error::UnnamedOperand(*c_mask))?;
+                let mut body = String::new();
+                self.write_op(op, &mut body)?;
+                for line in body.lines() {
+                    *source += &format!("if({c_reg}=={val}) {line}\n");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects the circuit into a single operator.
+    ///
+    /// Measurements are allowed only at the end of the circuit and are ignored,
+    /// any other non-unitary operation leads to an error.
+    pub fn unitary(&self) -> Result<MultiOp> {
+        let mut measured = 0;
+        let mut unitary = MultiOp::default();
+        for op in &self.ops {
+            match op {
+                Op::Measure(q, _) => measured |= q,
+                Op::Barrier(_) => {}
+                op => match op.unitary() {
+                    Some(op_unitary) if op.act_on() & measured == 0 => unitary *= op_unitary,
+                    _ => return Err(Error::NonUnitary(op.name())),
+                },
+            }
+        }
+        Ok(unitary)
+    }
+}
+
+/// Applies an operator to the wavefunction of `q_num` qubits.
+pub fn apply(op: &MultiOp, psi: &[C]) -> Vec<C> {
+    let mut psi_i = psi.to_vec();
+    psi_i.resize(psi.len().max(8), C::default());
+    let mut psi_o = vec![C::default(); psi_i.len()];
+    op.apply(&psi_i, &mut psi_o);
+    psi_o.truncate(psi.len());
+    psi_o
+}
+
+/// Lowers QASM `source` for tests.
+#[cfg(test)]
+pub(crate) fn circuit(source: &str) -> Circuit {
+    Circuit::from_asts(&[Ast::from_source(source).unwrap()]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_macros() {
+        let circuit = circuit(
+            "qreg q[2]; creg c[2];
+            gate foo(a) x, y { h x; crz(a) x, y; }
+            foo(pi) q[1], q[0];
+            measure q -> c;",
+        );
+
+        assert_eq!(circuit.q_num(), 2);
+        assert_eq!(circuit.c_num(), 2);
+        assert_eq!(circuit.ops.len(), 2);
+        match &circuit.ops[0] {
+            Op::Macro(gate, ops) => {
+                assert_eq!(gate.regs, vec![0b10, 0b01]);
+                assert_eq!(ops.len(), 2);
+            }
+            op => panic!("Unexpected op {:?}", op),
+        }
+        assert_eq!(circuit.ops[1], Op::Measure(0b11, 0b11));
+        assert!(circuit.unitary().is_ok());
+    }
+
+    #[test]
+    fn arg_format() {
+        assert_eq!(fmt_arg(std::f64::consts::PI), "pi");
+        assert_eq!(fmt_arg(-std::f64::consts::FRAC_PI_2), "-pi/2");
+        assert_eq!(fmt_arg(3.0 * std::f64::consts::FRAC_PI_4), "3pi/4");
+        assert_eq!(fmt_arg(0.5), "0.5");
+        assert_eq!(fmt_arg(0.0), "0");
+    }
+
+    #[test]
+    fn init_source() {
+        let circuit = circuit("qreg q[4]; qreg r[1]; h r;");
+        assert_eq!(
+            circuit.init_source("q", "0101"),
+            Ok("x q[0];x q[2];".to_string())
+        );
+        assert_eq!(circuit.init_source("q", "15"), Ok("x q;".to_string()));
+        assert_eq!(
+            circuit.init_source("r", "-"),
+            Ok("reset r;x r;h r;".to_string())
+        );
+        assert_eq!(
+            circuit.init_source("q", "16"),
+            Err(Error::InvalidInit("q".to_string(), "16".to_string()))
+        );
+    }
+
+    #[test]
+    fn qasm_roundtrip() {
+        let source = circuit(
+            "qreg q[2]; creg c[2];
+            gate foo(a) x, y { h x; crz(a) x, y; }
+            foo(3*pi/4) q[1], q[0]; rx(0.1) q[0];
+            measure q -> c; if(c==3) x q;",
+        )
+        .to_qasm()
+        .unwrap();
+
+        assert_eq!(
+            source,
+            "OPENQASM 2.0;
+gate foo(a) x, y { h x; crz(a) x, y; }
+qreg q[2];
+creg c[2];
+foo(3*pi/4) q[1], q[0];
+rx(0.1) q[0];
+measure q -> c;
+if(c==3) x q;
+"
+        );
+        let circuit = circuit(&source);
+        assert_eq!(circuit.to_qasm(), Ok(source));
+    }
+
+    #[test]
+    fn non_unitary() {
+        let circuit = circuit("qreg q[2]; creg c[2]; measure q -> c; h q[0];");
+        assert_eq!(circuit.unitary(), Err(Error::NonUnitary("h".to_string())));
+    }
+
+    #[test]
+    fn too_many_bits() {
+        let source = Ast::from_source("qreg a[40]; qreg b[40]; x b[30];").unwrap();
+        assert_eq!(
+            Circuit::from_asts(&[source]),
+            Err(Error::TooManyBits("b".to_string(), 80))
+        );
+
+        let circuit = circuit("qreg a[40]; qreg b[24]; creg c[64]; x b[23];");
+        assert_eq!(circuit.q_regs[1].mask(), !0 << 40);
+        assert_eq!(circuit.c_regs[0].mask(), !0);
+        assert_eq!(circuit.ops[0].act_on(), 1 << 63);
+    }
+}
@@ -1,17 +1,85 @@
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 #[derive(clap::Parser, Debug)]
 #[clap(name = "QVNT Interpreter", author, version, about, long_about = None)]
 pub struct CliArgs {
     #[clap(index(1), help = "OpenQASM input files")]
     pub inputs: Vec<PathBuf>,
-    
+
+    #[clap(
+        short = 'I',
+        long = "include",
+        value_name = "DIR",
+        help = "Directory to search included files in, after the directory of the including file"
+    )]
+    pub include_dirs: Vec<PathBuf>,
+
     #[clap(short = 'H', long, help = "History path for interpreter commands")]
     pub history: Option<PathBuf>,
 
+    #[clap(
+        short = 's',
+        long,
+        help = "Seed for measurement sampling, random if not specified"
+    )]
+    pub seed: Option<u64>,
+
+    #[clap(
+        short = 't',
+        long,
+        help = "Number of simulation threads, 1 for single-threaded mode [default: all cores]"
+    )]
+    pub threads: Option<NonZeroUsize>,
+
+    #[clap(
+        long,
+        value_parser = crate::memory::parse_size,
+        help = "Memory limit for simulation, e.g. 512M or 16G [default: available memory]"
+    )]
+    pub max_memory: Option<u64>,
+
+    #[clap(long, help = "Maximal number of simulated qubits")]
+    pub max_qubits: Option<usize>,
+
+    #[clap(long, help = "Report time spent on every processed line")]
+    pub timings: bool,
+
     #[cfg(feature = "tracing")]
     #[clap(short = 'l', long = "logs", help = "Logs file path")]
     pub logs_enabled: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    #[clap(
+        about = "Run *.qasm files with `// @expect` comments or companion *.expect files, checking expectations"
+    )]
+    Test {
+        #[clap(
+            default_value = ".",
+            help = "Directory (searched recursively) or file with tests"
+        )]
+        dir: PathBuf,
+
+        #[clap(long, help = "Save results to FILE as JUnit XML")]
+        junit: Option<PathBuf>,
+    },
+    #[clap(about = "Build the program from FILE without simulation and report suspicious places")]
+    Check {
+        #[clap(help = "OpenQASM file")]
+        file: PathBuf,
+    },
+    #[clap(about = "Rewrite FILE in canonical OpenQASM style")]
+    Fmt {
+        #[clap(help = "OpenQASM file")]
+        file: PathBuf,
+
+        #[clap(long, help = "Don't write the file, fail if it isn't formatted")]
+        check: bool,
+    },
 }
 
 impl CliArgs {
@@ -0,0 +1,358 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    circuit::{self, Circuit, Op},
+    sim::Sim,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownDebugCmd(String),
+    InvalidCount(String),
+}
+
+impl From<Error> for crate::lines::Error {
+    fn from(e: Error) -> Self {
+        Self::Debug(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownDebugCmd(cmd) => write!(f, "Unknown debugger command {cmd:?}"),
+            Error::InvalidCount(s) => write!(f, "Number of steps must be an integer: {s:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub const HELP: &str = "QVNT Interpreter debugger
+
+USAGE:
+    DBGCMD
+
+DBGCMD:
+    step|s [N]  Execute N operations (1 by default), stepping into macros
+    next|n      Execute the rest of current top-level operation, stepping over macros
+    continue|c  Execute operations until breakpoint or the end of the circuit
+    break|b [OP_INDEX|GATE_NAME|LABEL]
+                Pause before top-level operation OP_INDEX, before gates or macros named GATE_NAME
+                or labeled LABEL (e.g. `rz(pi/2)`), or list breakpoints
+    clear       Remove all breakpoints
+    where|w     Show the operation, which is executed next
+    prob|p      Show state of quantum registers in probability form
+    state       Show amplitudes of quantum registers
+    class       Show state of classical registers
+    quit|q      Leave debugger
+    help|h|?    Show this reference
+";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    Index(usize),
+    Name(String),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Index(idx) => write!(f, "op {idx}"),
+            Breakpoint::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Step(usize),
+    Next,
+    Continue,
+    Break(Option<Breakpoint>),
+    Clear,
+    Where,
+    Probs,
+    State,
+    Class,
+    Quit,
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Error> {
+        let mut source = source.trim_start_matches(':').split_ascii_whitespace();
+        match source.next() {
+            None | Some("where" | "w") => Ok(Command::Where),
+            Some("step" | "s") => match source.next() {
+                Some(count) => count
+                    .parse()
+                    .map(Command::Step)
+                    .map_err(|_| Error::InvalidCount(count.to_string())),
+                None => Ok(Command::Step(1)),
+            },
+            Some("next" | "n") => Ok(Command::Next),
+            Some("continue" | "c") => Ok(Command::Continue),
+            Some("break" | "b") => Ok(Command::Break(source.next().map(|arg| match arg.parse() {
+                Ok(idx) => Breakpoint::Index(idx),
+                Err(_) => Breakpoint::Name(arg.to_string()),
+            }))),
+            Some("clear") => Ok(Command::Clear),
+            Some("prob" | "p") => Ok(Command::Probs),
+            Some("state") => Ok(Command::State),
+            Some("class") => Ok(Command::Class),
+            Some("quit" | "q" | "exit") => Ok(Command::Quit),
+            Some("help" | "h" | "?") => Ok(Command::Help),
+            Some(cmd) => Err(Error::UnknownDebugCmd(cmd.to_string())),
+        }
+    }
+}
+
+/// Call of a macro, which encloses the step: unique id, name and label.
+type Scope = (usize, String, String);
+
+/// Single gate or non-unitary operation with its top-level operation and macros it's called from.
+#[derive(Clone, Debug)]
+struct Step {
+    top: usize,
+    scope: Vec<Scope>,
+    op: Op,
+}
+
+/// Executes the circuit operation by operation, pausing on breakpoints.
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    circuit: Circuit,
+    steps: Vec<Step>,
+    pos: usize,
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    pub fn new(circuit: Circuit) -> Self {
+        let mut steps = vec![];
+        let mut calls = 0;
+        for (top, op) in circuit.ops.iter().enumerate() {
+            Self::collect(op, top, &mut vec![], &mut calls, &mut steps);
+        }
+        Self {
+            circuit,
+            steps,
+            pos: 0,
+            breakpoints: vec![],
+        }
+    }
+
+    fn collect(
+        op: &Op,
+        top: usize,
+        scope: &mut Vec<Scope>,
+        calls: &mut usize,
+        steps: &mut Vec<Step>,
+    ) {
+        match op {
+            Op::Macro(gate, body) => {
+                *calls += 1;
+                scope.push((
+                    *calls,
+                    gate.name.clone(),
+                    circuit::label(&gate.name, &gate.args),
+                ));
+                for op in body {
+                    Self::collect(op, top, scope, calls, steps);
+                }
+                scope.pop();
+            }
+            Op::If(c_mask, val, op) if matches!(**op, Op::Macro(..)) => {
+                let start = steps.len();
+                Self::collect(op, top, scope, calls, steps);
+                for step in &mut steps[start..] {
+                    step.op = Op::If(*c_mask, *val, Box::new(step.op.clone()));
+                }
+            }
+            op => steps.push(Step {
+                top,
+                scope: scope.clone(),
+                op: op.clone(),
+            }),
+        }
+    }
+
+    /// Checks if any breakpoint points to the step at `pos`.
+    fn is_break(&self, pos: usize) -> bool {
+        let step = &self.steps[pos];
+        let prev = pos.checked_sub(1).map(|pos| &self.steps[pos]);
+        let top_entered = !matches!(prev, Some(prev) if prev.top == step.top);
+        let matches =
+            |name: &str, label: &str, bp: &str| name.eq_ignore_ascii_case(bp) || label == bp;
+
+        self.breakpoints.iter().any(|bp| match bp {
+            Breakpoint::Index(idx) => step.top == *idx && top_entered,
+            Breakpoint::Name(bp) => {
+                let (name, label) = match &step.op {
+                    Op::Gate(gate) => (gate.name.clone(), circuit::label(&gate.name, &gate.args)),
+                    op => (op.name(), op.name()),
+                };
+                matches(&name, &label, bp)
+                    || step
+                        .scope
+                        .iter()
+                        .enumerate()
+                        .any(|(depth, (id, name, label))| {
+                            let entered = top_entered
+                                || prev
+                                    .and_then(|prev| prev.scope.get(depth))
+                                    .map(|scope| scope.0)
+                                    != Some(*id);
+                            entered && matches(name, label, bp)
+                        })
+            }
+        })
+    }
+
+    /// Executes the next step and following ones while `keep_going` allows and no breakpoint is met.
+    fn advance(&mut self, sim: &mut Sim, mut keep_going: impl FnMut(&Step) -> bool) {
+        let mut first = true;
+        while let Some(step) = self.steps.get(self.pos) {
+            if !first && (!keep_going(step) || self.is_break(self.pos)) {
+                break;
+            }
+            first = false;
+            sim.step(&step.op);
+            self.pos += 1;
+        }
+    }
+
+    fn position(&self) -> String {
+        match self.steps.get(self.pos) {
+            None => format!("Finished: all {} operations are executed", self.steps.len()),
+            Some(step) => {
+                let source = self
+                    .circuit
+                    .op_to_qasm(&step.op)
+                    .unwrap_or_else(|_| step.op.name());
+                let scope: Vec<_> = step
+                    .scope
+                    .iter()
+                    .map(|(_, _, label)| label.as_str())
+                    .collect();
+                let scope = match scope.is_empty() {
+                    true => String::new(),
+                    false => format!(" in {}", scope.join(" > ")),
+                };
+                let prefix = match self.is_break(self.pos) {
+                    true => "Breakpoint at",
+                    false => "Next",
+                };
+                format!(
+                    "{prefix} [{}/{}] op {}{scope}: {source}",
+                    self.pos + 1,
+                    self.steps.len(),
+                    step.top
+                )
+            }
+        }
+    }
+
+    /// Processes the command. Returns `false`, if the debugger should be left.
+    pub fn process(&mut self, sim: &mut Sim, cmd: Command) -> bool {
+        match cmd {
+            Command::Step(count) => {
+                if count > 0 {
+                    let mut left = count - 1;
+                    self.advance(sim, |_| match left {
+                        0 => false,
+                        _ => {
+                            left -= 1;
+                            true
+                        }
+                    });
+                }
+                println!("{}", self.position());
+            }
+            Command::Next => {
+                let top = self.steps.get(self.pos).map(|step| step.top);
+                self.advance(sim, |step| Some(step.top) == top);
+                println!("{}", self.position());
+            }
+            Command::Continue => {
+                self.advance(sim, |_| true);
+                println!("{}", self.position());
+            }
+            Command::Break(Some(bp)) => self.breakpoints.push(bp),
+            Command::Break(None) => {
+                for (idx, bp) in self.breakpoints.iter().enumerate() {
+                    println!("{idx}: {bp}");
+                }
+            }
+            Command::Clear => self.breakpoints.clear(),
+            Command::Where => println!("{}", self.position()),
+            Command::Probs => println!("QReg probabilities: {:.4?}", sim.get_probabilities()),
+            Command::State => {
+                let state: Vec<_> = sim.state().iter().map(|z| format!("{z:.4}")).collect();
+                println!("QReg state: [{}]", state.join(", "));
+            }
+            Command::Class => println!("CReg: {}", sim.get_class()),
+            Command::Quit => return false,
+            Command::Help => println!("{}", HELP),
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn parse() {
+        assert_eq!("s 3".parse(), Ok(Command::Step(3)));
+        assert_eq!(":next".parse(), Ok(Command::Next));
+        assert_eq!(
+            "b 2".parse(),
+            Ok(Command::Break(Some(Breakpoint::Index(2))))
+        );
+        assert_eq!(
+            "break rz(pi/2)".parse(),
+            Ok(Command::Break(Some(Breakpoint::Name(
+                "rz(pi/2)".to_string()
+            ))))
+        );
+        assert_eq!(
+            "step x".parse::<Command>(),
+            Err(Error::InvalidCount("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn stepping() {
+        let source = "qreg q[2];
+            gate bell a, b { h a; cx a, b; }
+            x q[0]; bell q[0], q[1]; rz(pi/2) q[1]; bell q[1], q[0];";
+        let circuit = circuit(source);
+        let mut sim = Sim::default();
+        sim.init(circuit.clone()).unwrap();
+        let mut debugger = Debugger::new(circuit);
+        assert_eq!(debugger.steps.len(), 6);
+
+        debugger.process(&mut sim, Command::Step(2));
+        assert_eq!(debugger.pos, 2);
+        assert!((sim.get_probabilities()[0b01] - 0.5).abs() < 1e-12);
+
+        debugger.process(&mut sim, Command::Next);
+        assert_eq!(debugger.pos, 3);
+
+        debugger.process(
+            &mut sim,
+            Command::Break(Some(Breakpoint::Name("bell".to_string()))),
+        );
+        debugger.process(&mut sim, Command::Continue);
+        assert_eq!(debugger.pos, 4);
+
+        debugger.process(&mut sim, Command::Continue);
+        assert_eq!(debugger.pos, debugger.steps.len());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::circuit::{self, Circuit, Op, Register};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Wire,
+    Gate(String),
+    Ctrl,
+    Link,
+    DoubleLink,
+    Measure,
+    Cond,
+    Barrier,
+}
+
+/// Cells of a single column placement and the kind of vertical link between them:
+/// `None` for no link, `Some(false)` for quantum link and `Some(true)` for classical one.
+type Group = (Vec<(usize, Cell)>, Option<bool>);
+
+/// Rows range `lo..=hi` occupied by a single placement and the kind of link between them.
+pub type Span = (usize, usize, Option<bool>);
+
+const JOINT_GATES: &[&str] = &[
+    "rxx",
+    "ryy",
+    "rzz",
+    "swap",
+    "sqrt_swap",
+    "i_swap",
+    "sqrt_i_swap",
+    "qft",
+];
+
+/// Column placement of circuit operations, shared by all circuit renderers.
+/// Rows are qubits followed by classical bits.
+pub struct Layout {
+    pub q_num: usize,
+    pub columns: Vec<Vec<Cell>>,
+    pub spans: Vec<Vec<Span>>,
+    free: Vec<usize>,
+}
+
+impl Layout {
+    pub fn new(circuit: &Circuit) -> Self {
+        let q_num = circuit.q_num();
+        let rows = q_num + circuit.c_num();
+        let mut layout = Self {
+            q_num,
+            columns: vec![],
+            spans: vec![],
+            free: vec![0; rows],
+        };
+        for op in &circuit.ops {
+            for group in layout.groups(circuit, op) {
+                layout.place(group);
+            }
+        }
+        layout
+    }
+
+    fn groups(&self, circuit: &Circuit, op: &Op) -> Vec<Group> {
+        match op {
+            Op::Gate(gate) => {
+                let (ctrl, name) = gate.controls();
+                let label = circuit::label(name, &gate.args);
+                let ctrl_mask = gate.regs[..ctrl].iter().fold(0, |acc, reg| acc | reg);
+                let act_mask = gate.regs[ctrl..].iter().fold(0, |acc, reg| acc | reg);
+
+                let cells = circuit::bits(ctrl_mask)
+                    .map(|idx| (idx, Cell::Ctrl))
+                    .chain(circuit::bits(act_mask).map(|idx| (idx, Cell::Gate(label.clone()))))
+                    .collect();
+                let joint = ctrl != 0 || JOINT_GATES.contains(&name.to_lowercase().as_str());
+                vec![(cells, joint.then_some(false))]
+            }
+            Op::Macro(gate, _) => {
+                let label = circuit::label(&gate.name, &gate.args);
+                let cells = circuit::bits(gate.act_on())
+                    .map(|idx| (idx, Cell::Gate(label.clone())))
+                    .collect();
+                vec![(cells, Some(false))]
+            }
+            Op::Measure(q_mask, c_mask) => circuit::bits(*q_mask)
+                .zip(circuit::bits(*c_mask))
+                .map(|(q, c)| {
+                    let cells = vec![
+                        (q, Cell::Gate("M".to_string())),
+                        (self.q_num + c, Cell::Measure),
+                    ];
+                    (cells, Some(true))
+                })
+                .collect(),
+            Op::Reset(q_mask) => {
+                let cells = circuit::bits(*q_mask)
+                    .map(|idx| (idx, Cell::Gate("|0>".to_string())))
+                    .collect();
+                vec![(cells, None)]
+            }
+            Op::Barrier(q_mask) => {
+                let cells = circuit::bits(*q_mask)
+                    .map(|idx| (idx, Cell::Barrier))
+                    .collect();
+                vec![(cells, None)]
+            }
+            Op::If(c_mask, val, op) => {
+                let cond = circuit
+                    .c_regs
+                    .iter()
+                    .find(|reg| reg.mask() == *c_mask)
+                    .map_or_else(|| format!("c{c_mask:b}"), |reg| reg.name.clone());
+                self.groups(circuit, op)
+                    .into_iter()
+                    .map(|(cells, _)| {
+                        let cells = cells
+                            .into_iter()
+                            .map(|(idx, cell)| match cell {
+                                Cell::Gate(label) => {
+                                    (idx, Cell::Gate(format!("{label} if {cond}=={val}")))
+                                }
+                                cell => (idx, cell),
+                            })
+                            .chain(circuit::bits(*c_mask).map(|idx| (self.q_num + idx, Cell::Cond)))
+                            .collect();
+                        (cells, Some(true))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn place(&mut self, (cells, link): Group) {
+        let (lo, hi) = match (
+            cells.iter().map(|(idx, _)| *idx).min(),
+            cells.iter().map(|(idx, _)| *idx).max(),
+        ) {
+            (Some(lo), Some(hi)) => (lo, hi),
+            _ => return,
+        };
+
+        let col = self.free[lo..=hi].iter().cloned().max().unwrap_or(0);
+        while self.columns.len() <= col {
+            self.columns.push(vec![Cell::Wire; self.free.len()]);
+            self.spans.push(vec![]);
+        }
+        self.spans[col].push((lo, hi, link));
+
+        let column = &mut self.columns[col];
+        if let Some(double) = link {
+            for cell in &mut column[lo..=hi] {
+                *cell = if double { Cell::DoubleLink } else { Cell::Link };
+            }
+        }
+        for (idx, cell) in cells {
+            column[idx] = cell;
+        }
+        for free in &mut self.free[lo..=hi] {
+            *free = col + 1;
+        }
+    }
+
+    fn content(&self, row: usize, cell: &Cell) -> String {
+        let quantum = row < self.q_num;
+        match cell {
+            Cell::Wire => String::new(),
+            Cell::Gate(label) => format!("[{label}]"),
+            Cell::Ctrl => "■".to_string(),
+            Cell::Link => "┼".to_string(),
+            Cell::DoubleLink if quantum => "╫".to_string(),
+            Cell::DoubleLink => "╬".to_string(),
+            Cell::Measure => "╩".to_string(),
+            Cell::Cond => "●".to_string(),
+            Cell::Barrier => "░".to_string(),
+        }
+    }
+}
+
+fn center(content: &str, width: usize, fill: char) -> String {
+    let len = content.chars().count();
+    let left = (width - len) / 2;
+    let right = width - len - left;
+    let fill = |n| fill.to_string().repeat(n);
+    format!("{}{}{}", fill(left), content, fill(right))
+}
+
+/// Wire labels of the circuit: register aliases of qubits followed by ones of classical bits.
+pub fn labels(circuit: &Circuit) -> Vec<String> {
+    (0..circuit.q_num())
+        .filter_map(|idx| Register::bit_name(&circuit.q_regs, idx))
+        .chain((0..circuit.c_num()).filter_map(|idx| Register::bit_name(&circuit.c_regs, idx)))
+        .collect()
+}
+
+/// Renders the circuit as a text diagram, wrapping it to fit into `width` characters.
+pub fn render(circuit: &Circuit, width: usize) -> String {
+    let layout = Layout::new(circuit);
+    let labels = labels(circuit);
+    if labels.is_empty() {
+        return String::new();
+    }
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let contents: Vec<Vec<String>> = layout
+        .columns
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .enumerate()
+                .map(|(row, cell)| layout.content(row, cell))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = contents
+        .iter()
+        .map(|column| column.iter().map(|c| c.chars().count()).max().unwrap_or(0) + 2)
+        .collect();
+
+    let avail = width.saturating_sub(label_width + 4).max(1);
+    let mut pages = vec![];
+    let (mut start, mut used) = (0, 0);
+    for (idx, width) in widths.iter().enumerate() {
+        if used + width > avail && idx > start {
+            pages.push(start..idx);
+            start = idx;
+            used = 0;
+        }
+        used += width;
+    }
+    pages.push(start..widths.len());
+
+    let mut out = String::new();
+    for (page_idx, page) in pages.iter().enumerate() {
+        if page_idx != 0 {
+            out.push('\n');
+        }
+        for (row, label) in labels.iter().enumerate() {
+            let fill = if row < layout.q_num { '─' } else { '═' };
+            out += &format!("{label:>label_width$}: {fill}");
+            for col in page.clone() {
+                out += &center(&contents[col][row], widths[col], fill);
+            }
+            out.push(fill);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn bell_state() {
+        let source = "qreg q[2]; creg c[2]; h q[0]; cx q[0], q[1]; measure q -> c;";
+        let circuit = circuit(source);
+        let diagram = render(&circuit, 80);
+        let lines: Vec<_> = diagram.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "q[0]: ──[h]───■───[M]───────");
+        assert_eq!(lines[1], "q[1]: ───────[x]───╫───[M]──");
+        assert_eq!(lines[2], "c[0]: ═════════════╩════╬═══");
+        assert_eq!(lines[3], "c[1]: ══════════════════╩═══");
+    }
+
+    #[test]
+    fn wrapping() {
+        let source = "qreg q[1]; h q; h q; h q; h q; h q; h q;";
+        let circuit = circuit(source);
+        let diagram = render(&circuit, 25);
+
+        assert!(diagram.lines().all(|line| line.chars().count() <= 25));
+        assert_eq!(diagram.lines().filter(|line| line.is_empty()).count(), 1);
+    }
+}
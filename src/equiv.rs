@@ -0,0 +1,185 @@
+use std::fmt;
+
+use num_complex::Complex64 as C;
+use qvnt::prelude::MultiOp;
+use rand::Rng;
+
+use crate::circuit::{self, Circuit};
+
+/// Circuits up to this size are compared column by column of their unitaries.
+pub const EXACT_MAX_QUBITS: usize = 10;
+const PROBES: usize = 16;
+const EPS: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Exact,
+    Probing(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Equivalent,
+    Differ { basis: Option<usize>, fidelity: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub q_num: usize,
+    pub method: Method,
+    pub verdict: Verdict,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = match self.method {
+            Method::Exact => "exact check".to_string(),
+            Method::Probing(probes) => format!("{probes} random states"),
+        };
+        match self.verdict {
+            Verdict::Equivalent => write!(
+                f,
+                "Circuits are equivalent up to global phase ({} qubits, {method})",
+                self.q_num
+            ),
+            Verdict::Differ {
+                basis: Some(basis),
+                fidelity,
+            } => write!(
+                f,
+                "Circuits differ on input |{basis:0width$b}> (fidelity {fidelity:.6}, {method})",
+                width = self.q_num.max(1)
+            ),
+            Verdict::Differ {
+                basis: None,
+                fidelity,
+            } => write!(
+                f,
+                "Circuits differ on superposed input, but not on first basis states (fidelity {fidelity:.6}, {method})"
+            ),
+        }
+    }
+}
+
+fn basis(q_num: usize, idx: usize) -> Vec<C> {
+    let mut psi = vec![C::default(); 1 << q_num];
+    psi[idx] = C::new(1.0, 0.0);
+    psi
+}
+
+fn random_state(q_num: usize, rng: &mut impl Rng) -> Vec<C> {
+    let psi: Vec<C> = (0..1_usize << q_num)
+        .map(|_| C::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+        .collect();
+    let norm = psi.iter().map(C::norm_sqr).sum::<f64>().sqrt();
+    psi.into_iter().map(|z| z / norm).collect()
+}
+
+fn overlap(lhs: &[C], rhs: &[C]) -> C {
+    lhs.iter().zip(rhs).map(|(l, r)| l.conj() * r).sum()
+}
+
+/// Scans basis states and returns the first one, which output differs,
+/// taking into account the phase of the previous outputs.
+fn first_distinct(
+    q_num: usize,
+    lhs: &MultiOp,
+    rhs: &MultiOp,
+    limit: usize,
+) -> Option<(usize, f64)> {
+    let mut phase = None;
+    for idx in 0..limit {
+        let psi = basis(q_num, idx);
+        let ovl = overlap(&circuit::apply(lhs, &psi), &circuit::apply(rhs, &psi));
+        if (ovl.norm() - 1.0).abs() > EPS {
+            return Some((idx, ovl.norm_sqr()));
+        }
+        match phase {
+            None => phase = Some(ovl),
+            Some(phase) if (ovl - phase).norm() > EPS => return Some((idx, ovl.norm_sqr())),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Checks unitary equivalence of circuits up to global phase.
+pub fn check(lhs: &Circuit, rhs: &Circuit) -> circuit::Result<Report> {
+    let q_num = lhs.q_num();
+    if q_num != rhs.q_num() {
+        return Err(circuit::Error::UnmatchedQubits(q_num, rhs.q_num()));
+    }
+    let (lhs, rhs) = (lhs.unitary()?, rhs.unitary()?);
+
+    let (method, verdict) = if q_num <= EXACT_MAX_QUBITS {
+        let verdict = match first_distinct(q_num, &lhs, &rhs, 1 << q_num) {
+            Some((basis, fidelity)) => Verdict::Differ {
+                basis: Some(basis),
+                fidelity,
+            },
+            None => Verdict::Equivalent,
+        };
+        (Method::Exact, verdict)
+    } else {
+        let mut rng = rand::thread_rng();
+        let failed = (0..PROBES).find_map(|_| {
+            let psi = random_state(q_num, &mut rng);
+            let ovl = overlap(&circuit::apply(&lhs, &psi), &circuit::apply(&rhs, &psi));
+            (ovl.norm_sqr() < 1.0 - EPS).then(|| ovl.norm_sqr())
+        });
+        let verdict = match failed {
+            Some(fidelity) => match first_distinct(q_num, &lhs, &rhs, 1 << EXACT_MAX_QUBITS) {
+                Some((basis, fidelity)) => Verdict::Differ {
+                    basis: Some(basis),
+                    fidelity,
+                },
+                None => Verdict::Differ {
+                    basis: None,
+                    fidelity,
+                },
+            },
+            None => Verdict::Equivalent,
+        };
+        (Method::Probing(PROBES), verdict)
+    };
+
+    Ok(Report {
+        q_num,
+        method,
+        verdict,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn equivalent() {
+        let lhs = circuit("qreg q[2]; h q[0]; h q[0]; cx q[0], q[1];");
+        let rhs = circuit("qreg q[2]; cx q[0], q[1]; x q[1]; x q[1];");
+        assert_eq!(check(&lhs, &rhs).unwrap().verdict, Verdict::Equivalent);
+
+        let lhs = circuit("qreg q[1]; z q[0];");
+        let rhs = circuit("qreg q[1]; rz(pi) q[0];");
+        assert_eq!(check(&lhs, &rhs).unwrap().verdict, Verdict::Equivalent);
+    }
+
+    #[test]
+    fn distinct() {
+        let lhs = circuit("qreg q[2]; cx q[0], q[1];");
+        let rhs = circuit("qreg q[2]; cx q[1], q[0];");
+        match check(&lhs, &rhs).unwrap().verdict {
+            Verdict::Differ { basis, .. } => assert_eq!(basis, Some(0b01)),
+            verdict => panic!("Unexpected verdict {:?}", verdict),
+        }
+
+        let lhs = circuit("qreg q[1]; s q[0];");
+        let rhs = circuit("qreg q[1]; z q[0];");
+        match check(&lhs, &rhs).unwrap().verdict {
+            Verdict::Differ { basis, .. } => assert_eq!(basis, Some(0b1)),
+            verdict => panic!("Unexpected verdict {:?}", verdict),
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::str::FromStr;
+
+use crate::{
+    circuit::Circuit,
+    draw::{self, Cell, Layout, Span},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Latex,
+    Svg,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "latex" | "tex" | "quantikz" => Ok(Self::Latex),
+            "svg" => Ok(Self::Svg),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Renders the circuit into the source of the given format.
+pub fn render(circuit: &Circuit, format: Format) -> String {
+    match format {
+        Format::Latex => latex(circuit),
+        Format::Svg => svg(circuit),
+    }
+}
+
+fn span_of(spans: &[Span], row: usize) -> Option<Span> {
+    spans
+        .iter()
+        .find(|(lo, hi, _)| (*lo..=*hi).contains(&row))
+        .cloned()
+}
+
+/// Controlled `x` gates are drawn as a target instead of a box.
+fn is_target(column: &[Cell], span: Option<Span>, label: &str) -> bool {
+    match span {
+        Some((lo, hi, Some(false))) if label == "x" || label == "X" => {
+            column[lo..=hi].contains(&Cell::Ctrl)
+        }
+        _ => false,
+    }
+}
+
+fn latex_label(label: &str) -> String {
+    let (name, rest) = match label.find(['(', '[', ' ']) {
+        Some(idx) => label.split_at(idx),
+        None => (label, ""),
+    };
+    let name = match name {
+        "|0>" => return r"\lvert 0 \rangle".to_string(),
+        name => name.replace('_', r"\_"),
+    };
+    let rest = rest
+        .replace("pi", r"\pi ")
+        .replace("==", "=")
+        .replace(" if ", r"\ \mathrm{if}\ ")
+        .replace('_', r"\_");
+    format!(r"\mathrm{{{name}}}{rest}")
+}
+
+/// Renders the circuit as a `quantikz` environment.
+pub fn latex(circuit: &Circuit) -> String {
+    let layout = Layout::new(circuit);
+    let labels = draw::labels(circuit);
+
+    let mut out = String::from("% requires \\usepackage{tikz} and \\usetikzlibrary{quantikz}\n");
+    out += "\\begin{quantikz}\n";
+    for (row, label) in labels.iter().enumerate() {
+        let quantum = row < layout.q_num;
+        let wire = if quantum { r"\qw" } else { r"\cw" };
+        // `\lstick` is typeset in text mode, unlike gate labels
+        out += &format!(r"\lstick{{${}$}}", latex_label(label));
+
+        for (column, spans) in layout.columns.iter().zip(&layout.spans) {
+            let span = span_of(spans, row);
+            let mut cell = match &column[row] {
+                Cell::Wire | Cell::Link | Cell::DoubleLink | Cell::Measure => wire.to_string(),
+                Cell::Gate(label) if is_target(column, span, label) => r"\targ{}".to_string(),
+                Cell::Gate(label) if label == "M" => r"\meter{}".to_string(),
+                Cell::Gate(label) => format!(r"\gate{{{}}}", latex_label(label)),
+                Cell::Ctrl | Cell::Cond => r"\control{}".to_string(),
+                Cell::Barrier => r"\qw".to_string(),
+            };
+            match span {
+                Some((lo, hi, Some(double))) if lo == row && hi > lo => {
+                    if column[row] == Cell::Ctrl {
+                        cell = format!(r"\ctrl{{{}}}", hi - lo);
+                    } else {
+                        cell += &format!(r" \v{}w{{{}}}", if double { 'c' } else { 'q' }, hi - lo);
+                    }
+                }
+                Some((lo, _, None)) if lo == row && column[row] == Cell::Barrier => {
+                    cell += r" \slice{}";
+                }
+                _ => {}
+            }
+            out += " & ";
+            out += &cell;
+        }
+
+        out += " & ";
+        out += wire;
+        if row + 1 != labels.len() {
+            out += r" \\";
+        }
+        out.push('\n');
+    }
+    out += "\\end{quantikz}\n";
+    out
+}
+
+const ROW: f64 = 40.0;
+const MARGIN: f64 = 10.0;
+const CHAR: f64 = 8.5;
+const BOX: f64 = 30.0;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn box_width(label: &str) -> f64 {
+    (label.chars().count() as f64 * CHAR + 12.0).max(BOX)
+}
+
+/// Renders the circuit as a standalone SVG image.
+pub fn svg(circuit: &Circuit) -> String {
+    let layout = Layout::new(circuit);
+    let labels = draw::labels(circuit);
+    let y = |row: usize| MARGIN + (row as f64 + 0.5) * ROW;
+
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count() as f64 * CHAR)
+        .fold(0.0, f64::max)
+        + 2.0 * MARGIN;
+    let widths: Vec<f64> = layout
+        .columns
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Gate(label) => box_width(label),
+                    _ => BOX,
+                })
+                .fold(0.0, f64::max)
+                + MARGIN
+        })
+        .collect();
+    let width = label_width + widths.iter().sum::<f64>() + 2.0 * MARGIN;
+    let height = labels.len() as f64 * ROW + 2.0 * MARGIN;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+        viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"14\">\n"
+    );
+    out += "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n";
+
+    for (row, label) in labels.iter().enumerate() {
+        let y = y(row);
+        out += &format!(
+            "<text x=\"{}\" y=\"{y}\" text-anchor=\"end\" dominant-baseline=\"middle\">{}</text>\n",
+            label_width - MARGIN,
+            escape(label)
+        );
+        if row < layout.q_num {
+            out += &format!(
+                "<line x1=\"{label_width}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"black\"/>\n",
+                width - MARGIN
+            );
+        } else {
+            for y in [y - 1.5, y + 1.5] {
+                out += &format!(
+                    "<line x1=\"{label_width}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"black\"/>\n",
+                    width - MARGIN
+                );
+            }
+        }
+    }
+
+    let mut left = label_width;
+    for ((column, spans), col_width) in layout.columns.iter().zip(&layout.spans).zip(&widths) {
+        let x = left + col_width / 2.0;
+        left += col_width;
+
+        for &(lo, hi, link) in spans {
+            match link {
+                Some(false) => {
+                    out += &format!(
+                        "<line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"black\"/>\n",
+                        y(lo),
+                        y(hi)
+                    );
+                }
+                Some(true) => {
+                    for x in [x - 1.5, x + 1.5] {
+                        out += &format!(
+                            "<line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"black\"/>\n",
+                            y(lo),
+                            y(hi)
+                        );
+                    }
+                }
+                None if column[lo] == Cell::Barrier => {
+                    out += &format!(
+                        "<line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"gray\" stroke-dasharray=\"4,3\"/>\n",
+                        y(lo) - ROW / 2.0,
+                        y(hi) + ROW / 2.0
+                    );
+                }
+                None => {}
+            }
+        }
+
+        for (row, cell) in column.iter().enumerate() {
+            let y = y(row);
+            match cell {
+                Cell::Gate(label) if is_target(column, span_of(spans, row), label) => {
+                    out += &format!(
+                        "<circle cx=\"{x}\" cy=\"{y}\" r=\"10\" fill=\"white\" stroke=\"black\"/>\n\
+                        <line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"black\"/>\n\
+                        <line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"black\"/>\n",
+                        x - 10.0,
+                        x + 10.0,
+                        y - 10.0,
+                        y + 10.0
+                    );
+                }
+                Cell::Gate(label) => {
+                    let w = box_width(label);
+                    out += &format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{w}\" height=\"{BOX}\" fill=\"white\" stroke=\"black\"/>\n\
+                        <text x=\"{x}\" y=\"{y}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>\n",
+                        x - w / 2.0,
+                        y - BOX / 2.0,
+                        escape(label)
+                    );
+                }
+                Cell::Ctrl | Cell::Cond => {
+                    out += &format!("<circle cx=\"{x}\" cy=\"{y}\" r=\"5\" fill=\"black\"/>\n");
+                }
+                Cell::Measure => {
+                    out += &format!(
+                        "<polygon points=\"{},{} {},{} {x},{y}\" fill=\"black\"/>\n",
+                        x - 5.0,
+                        y - 8.0,
+                        x + 5.0,
+                        y - 8.0
+                    );
+                }
+                Cell::Wire | Cell::Link | Cell::DoubleLink | Cell::Barrier => {}
+            }
+        }
+    }
+
+    out += "</svg>\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn bell_state() {
+        let source = "qreg q[2]; creg c[2]; h q[0]; cx q[0], q[1]; measure q -> c;";
+        let circuit = circuit(source);
+
+        let latex = latex(&circuit);
+        let lines: Vec<_> = latex.lines().skip(2).collect();
+        assert_eq!(
+            lines[0],
+            r"\lstick{$\mathrm{q}[0]$} & \gate{\mathrm{h}} & \ctrl{1} & \meter{} \vcw{2} & \qw & \qw \\"
+        );
+        assert_eq!(
+            lines[1],
+            r"\lstick{$\mathrm{q}[1]$} & \qw & \targ{} & \qw & \meter{} \vcw{2} & \qw \\"
+        );
+        assert_eq!(lines[4], r"\end{quantikz}");
+
+        let svg = svg(&circuit);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect ").count(), 4);
+        assert_eq!(svg.matches(">c[1]</text>").count(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, path::Path};
+
+use qasm::{Argument, AstNode};
+use qvnt::qasm::Ast;
+
+use crate::{
+    include,
+    process::{self, Process},
+    program::{ProgramError, ProgramResult},
+};
+
+const HEADER: &str = "OPENQASM 2.0;";
+/// Indentation of gate bodies.
+const INDENT: &str = "  ";
+
+/// Comments, attached to a statement.
+#[derive(Default)]
+struct Comments<'t> {
+    /// Comments on their own lines before the statement, with their offsets.
+    leading: Vec<(usize, &'t str)>,
+    /// Comments after the code on lines of the statement.
+    trailing: Vec<&'t str>,
+}
+
+/// Writes statements in canonical style, keeping comments of the source.
+///
+/// Every statement is identified by its anchor: the offset of its first name in the source.
+/// Comments on their own lines go before the next anchor, other comments go after the previous one.
+struct Formatter<'t> {
+    source: &'t str,
+    /// Source with comments replaced by spaces.
+    code: String,
+    comments: HashMap<usize, Comments<'t>>,
+    /// Comments after the last statement.
+    footer: Vec<(usize, &'t str)>,
+    output: String,
+}
+
+impl<'t> Formatter<'t> {
+    fn new(source: &'t str) -> Self {
+        let mut code = source.to_string();
+        for (offset, comment) in Self::scan_comments(source) {
+            code.replace_range(offset..offset + comment.len(), &" ".repeat(comment.len()));
+        }
+        Self {
+            source,
+            code,
+            comments: HashMap::new(),
+            footer: vec![],
+            output: String::new(),
+        }
+    }
+
+    /// Comments of the source with their offsets.
+    fn scan_comments(source: &'t str) -> Vec<(usize, &'t str)> {
+        let mut comments = vec![];
+        let mut line_start = 0;
+        for line in source.split('\n') {
+            if let Some(pos) = line.find("//") {
+                comments.push((line_start + pos, line[pos..].trim_end()));
+            }
+            line_start += line.len() + 1;
+        }
+        comments
+    }
+
+    /// Attaches comments to the nearest of `anchors`.
+    fn attach_comments(&mut self, mut anchors: Vec<usize>) {
+        anchors.sort_unstable();
+        for (offset, comment) in Self::scan_comments(self.source) {
+            let idx = anchors.partition_point(|&anchor| anchor < offset);
+            let line_start = self.source[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+            let trailing = !self.source[line_start..offset].trim().is_empty();
+            match (trailing, idx) {
+                (true, idx) if idx > 0 => self
+                    .comments
+                    .entry(anchors[idx - 1])
+                    .or_default()
+                    .trailing
+                    .push(comment),
+                (_, idx) if idx < anchors.len() => self
+                    .comments
+                    .entry(anchors[idx])
+                    .or_default()
+                    .leading
+                    .push((offset, comment)),
+                _ => self.footer.push((offset, comment)),
+            }
+        }
+    }
+
+    fn offset(&self, slice: &str) -> usize {
+        slice.as_ptr() as usize - self.source.as_ptr() as usize
+    }
+
+    fn anchor(&self, node: &AstNode<'_>) -> usize {
+        match node {
+            AstNode::QReg(name, _)
+            | AstNode::CReg(name, _)
+            | AstNode::ApplyGate(name, ..)
+            | AstNode::Opaque(name, ..)
+            | AstNode::Gate(name, ..)
+            | AstNode::If(name, ..) => self.offset(name),
+            AstNode::Barrier(arg) | AstNode::Reset(arg) | AstNode::Measure(arg, _) => {
+                self.offset(arg_name(arg))
+            }
+        }
+    }
+
+    /// Offset of the closing brace of the gate definition, or its anchor for other statements.
+    fn end(&self, node: &AstNode<'_>) -> usize {
+        match node {
+            AstNode::Gate(_, _, _, body) => {
+                let from = body
+                    .last()
+                    .map_or(self.anchor(node), |node| self.anchor(node));
+                self.code[from..].find('}').map_or(from, |pos| from + pos)
+            }
+            _ => self.anchor(node),
+        }
+    }
+
+    /// Offset, where the statement starts with its leading comments.
+    fn start(&self, anchor: usize) -> usize {
+        self.comments
+            .get(&anchor)
+            .and_then(|comments| comments.leading.first())
+            .map_or(anchor, |(offset, _)| *offset)
+    }
+
+    /// Checks there is an empty line in the source between offsets `prev` and `next`.
+    fn blank_line(&self, prev: usize, next: usize) -> bool {
+        let line_start = self.source[..next].rfind('\n').map_or(0, |idx| idx + 1);
+        prev < line_start
+            && self.source[prev..line_start - 1]
+                .split('\n')
+                .skip(1)
+                .any(|line| line.trim().is_empty())
+    }
+
+    fn line(&mut self, indent: &str, text: &str, trailing: &[&str]) {
+        self.output += indent;
+        self.output += text;
+        for comment in trailing {
+            self.output += " ";
+            self.output += comment;
+        }
+        self.output += "\n";
+    }
+
+    /// Writes the statement with its comments.
+    fn emit(&mut self, indent: &str, anchor: usize, text: &str) {
+        let comments = self.comments.remove(&anchor).unwrap_or_default();
+        for (_, comment) in comments.leading {
+            self.line(indent, comment, &[]);
+        }
+        self.line(indent, text, &comments.trailing);
+    }
+
+    fn definition(&mut self, node: &AstNode<'t>) {
+        match node {
+            AstNode::Gate(name, regs, args, body) => {
+                let close = self.comments.remove(&self.end(node)).unwrap_or_default();
+                if body.is_empty() && close.leading.is_empty() {
+                    let mut comments = self.comments.remove(&self.anchor(node)).unwrap_or_default();
+                    for (_, comment) in comments.leading {
+                        self.line("", comment, &[]);
+                    }
+                    comments.trailing.extend(close.trailing);
+                    self.line("", &statement(node), &comments.trailing);
+                    return;
+                }
+
+                let header = format!("gate {name}{} {} {{", params(args), regs.join(", "));
+                self.emit("", self.anchor(node), &header);
+                for node in body {
+                    self.emit(INDENT, self.anchor(node), &statement(node));
+                }
+                for (_, comment) in close.leading {
+                    self.line(INDENT, comment, &[]);
+                }
+                self.line("", "}", &close.trailing);
+            }
+            _ => self.emit("", self.anchor(node), &statement(node)),
+        }
+    }
+}
+
+fn arg_name<'t>(arg: &Argument<'t>) -> &'t str {
+    match arg {
+        Argument::Qubit(name, _) | Argument::Register(name) => name,
+    }
+}
+
+fn argument(arg: &Argument<'_>) -> String {
+    match arg {
+        Argument::Qubit(name, idx) => format!("{name}[{idx}]"),
+        Argument::Register(name) => name.to_string(),
+    }
+}
+
+fn arguments(args: &[Argument<'_>]) -> String {
+    args.iter().map(argument).collect::<Vec<_>>().join(", ")
+}
+
+/// Parameters in parentheses with spaces removed from expressions, or nothing if there are none.
+fn params(args: &[&str]) -> String {
+    if args.is_empty() {
+        return String::new();
+    }
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| arg.split_whitespace().collect())
+        .collect();
+    format!("({})", args.join(", "))
+}
+
+/// Writes a single statement, gate definitions are written with empty bodies.
+fn statement(node: &AstNode<'_>) -> String {
+    match node {
+        AstNode::QReg(name, size) => format!("qreg {name}[{size}];"),
+        AstNode::CReg(name, size) => format!("creg {name}[{size}];"),
+        AstNode::Barrier(arg) => format!("barrier {};", argument(arg)),
+        AstNode::Reset(arg) => format!("reset {};", argument(arg)),
+        AstNode::Measure(q_arg, c_arg) => {
+            format!("measure {} -> {};", argument(q_arg), argument(c_arg))
+        }
+        AstNode::ApplyGate(name, regs, args) => {
+            format!("{name}{} {};", params(args), arguments(regs))
+        }
+        AstNode::Opaque(name, regs, args) => {
+            format!("opaque {name}{} {};", params(args), arguments(regs))
+        }
+        AstNode::Gate(name, regs, args, _) => {
+            format!("gate {name}{} {} {{ }}", params(args), regs.join(", "))
+        }
+        AstNode::If(name, val, node) => format!("if({name}=={val}) {}", statement(node)),
+    }
+}
+
+/// Writes the program in canonical style: the header and includes, gate definitions,
+/// quantum and classical registers, and then other statements in the original order.
+/// Comments stay with statements they precede or follow on the same line,
+/// empty lines between statements are collapsed to a single one.
+pub fn format(ast: &Ast<'_>) -> String {
+    let source = ast.source();
+    let nodes: Vec<AstNode<'_>> = ast.clone().into_iter().collect();
+    let mut f = Formatter::new(source);
+
+    let header = f.code.find("OPENQASM");
+    let includes = include::statements(source);
+    let mut anchors: Vec<usize> = header.into_iter().collect();
+    anchors.extend(includes.iter().map(|(anchor, _)| *anchor));
+    for node in &nodes {
+        anchors.push(f.anchor(node));
+        if let AstNode::Gate(_, _, _, body) = node {
+            anchors.extend(body.iter().map(|node| f.anchor(node)));
+            anchors.push(f.end(node));
+        }
+    }
+    f.attach_comments(anchors);
+
+    match header {
+        Some(anchor) => f.emit("", anchor, HEADER),
+        None => f.line("", HEADER, &[]),
+    }
+    for (anchor, path) in includes {
+        f.emit("", anchor, &format!("include \"{path}\";"));
+    }
+
+    let is_definition =
+        |node: &&AstNode<'_>| matches!(node, AstNode::Gate(..) | AstNode::Opaque(..));
+    for node in nodes.iter().filter(is_definition) {
+        f.output += "\n";
+        f.definition(node);
+    }
+
+    let mut declarations = nodes
+        .iter()
+        .filter(|node| matches!(node, AstNode::QReg(..)))
+        .chain(
+            nodes
+                .iter()
+                .filter(|node| matches!(node, AstNode::CReg(..))),
+        )
+        .peekable();
+    if declarations.peek().is_some() {
+        f.output += "\n";
+    }
+    for node in declarations {
+        f.emit("", f.anchor(node), &statement(node));
+    }
+
+    let mut prev = None;
+    for node in nodes.iter().filter(|node| {
+        !is_definition(node) && !matches!(node, AstNode::QReg(..) | AstNode::CReg(..))
+    }) {
+        let anchor = f.anchor(node);
+        if prev.is_none_or(|prev| f.blank_line(prev, f.start(anchor))) {
+            f.output += "\n";
+        }
+        f.emit("", anchor, &statement(node));
+        prev = Some(anchor);
+    }
+
+    let footer = std::mem::take(&mut f.footer);
+    if let Some((offset, _)) = footer.first() {
+        if prev.is_none_or(|prev| f.blank_line(prev, *offset)) {
+            f.output += "\n";
+        }
+    }
+    for (_, comment) in footer {
+        f.line("", comment, &[]);
+    }
+    f.output
+}
+
+/// Runs the `fmt` subcommand: rewrites the file in canonical style,
+/// or only checks it's already formatted.
+pub fn run(path: &Path, check: bool) -> ProgramResult {
+    let source = std::fs::read_to_string(path).map_err(process::Error::Io)?;
+    let ast = Process::ast_from_string(source.clone())?;
+    let formatted = format(&ast);
+    if formatted == source {
+        return Ok(());
+    }
+
+    if check {
+        let line = source
+            .lines()
+            .zip(formatted.lines())
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| source.lines().count().min(formatted.lines().count()));
+        println!("{}:{}: not formatted", path.display(), line + 1);
+        Err(ProgramError::Unformatted(path.to_path_buf()))
+    } else {
+        std::fs::write(path, formatted).map_err(process::Error::Io)?;
+        println!("Formatted {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical() {
+        let source = "// Header comment
+OPENQASM 2.0;
+include  \"qelib1.inc\";
+qreg q[2];
+gate foo ( theta ) a,b
+{ // body
+  rx( theta / 2 ) a; cx a,b;
+  // closing
+}
+creg c[2];   // result
+h q[0]; cx q[0],q[1];
+
+
+// measure all
+measure q->c;
+qreg r[1];
+if (c==3) x r[0];
+// tail
+";
+        let expected = "// Header comment
+OPENQASM 2.0;
+include \"qelib1.inc\";
+
+gate foo(theta) a, b { // body
+  rx(theta/2) a;
+  cx a, b;
+  // closing
+}
+
+qreg q[2];
+qreg r[1];
+creg c[2]; // result
+
+h q[0];
+cx q[0], q[1];
+
+// measure all
+measure q -> c;
+if(c==3) x r[0];
+// tail
+";
+        let ast = Ast::from_source(source).unwrap();
+        assert_eq!(format(&ast), expected);
+
+        let ast = Ast::from_source(expected).unwrap();
+        assert_eq!(format(&ast), expected);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Name of the standard library, which is always resolved to the embedded one.
+pub const QELIB1: &str = "qelib1.inc";
+/// Standard gates of OpenQASM 2.0, defined through gates of `qvnt`.
+const QELIB1_SOURCE: &str = include_str!("qelib1.inc");
+/// Environment variable with additional directories to search include files in.
+pub const INCLUDE_PATH_VAR: &str = "QVNT_INCLUDE_PATH";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    NotFound(String),
+    Cycle(Vec<PathBuf>),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(name) => write!(f, "Cannot find include file {name:?}"),
+            Error::Cycle(files) => {
+                let files: Vec<_> = files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect();
+                write!(f, "Include cycle: {}", files.join(" -> "))
+            }
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Included file with its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    /// Canonical path of the file, or [`QELIB1`] for the embedded library.
+    pub path: PathBuf,
+    pub source: String,
+}
+
+/// Finds `include` statements, which are dropped by the parser.
+/// Returns offsets of `include` keywords with paths of included files.
+pub fn statements(source: &str) -> Vec<(usize, &str)> {
+    let mut statements = vec![];
+    let mut line_start = 0;
+    for line in source.split('\n') {
+        let code = &line[..line.find("//").unwrap_or(line.len())];
+        let mut from = 0;
+        while let Some(pos) = code[from..].find("include") {
+            let start = from + pos;
+            from = start + "include".len();
+            if code[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+            {
+                continue;
+            }
+            let path = match code[from..].trim_start().strip_prefix('"') {
+                Some(path) => path,
+                None => continue,
+            };
+            if let Some(len) = path.find('"') {
+                if path[len + 1..].trim_start().starts_with(';') {
+                    statements.push((line_start + start, &path[..len]));
+                }
+            }
+        }
+        line_start += line.len() + 1;
+    }
+    statements
+}
+
+/// Directories to search include files in, after the directory of the including file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchPath {
+    dirs: Vec<PathBuf>,
+}
+
+impl SearchPath {
+    /// Searches in `dirs` and then in directories of [`INCLUDE_PATH_VAR`].
+    pub fn new(mut dirs: Vec<PathBuf>) -> Self {
+        if let Some(paths) = std::env::var_os(INCLUDE_PATH_VAR) {
+            dirs.extend(std::env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()));
+        }
+        Self { dirs }
+    }
+
+    fn find(&self, name: &str, dir: &Path) -> Result<Include> {
+        if name == QELIB1 {
+            return Ok(Include {
+                path: PathBuf::from(QELIB1),
+                source: QELIB1_SOURCE.to_string(),
+            });
+        }
+        for dir in std::iter::once(dir).chain(self.dirs.iter().map(PathBuf::as_path)) {
+            let path = dir.join(name);
+            if path.is_file() {
+                let path = path.canonicalize().unwrap_or(path);
+                return match std::fs::read_to_string(&path) {
+                    Ok(source) => Ok(Include { path, source }),
+                    Err(_) => Err(Error::NotFound(name.to_string())),
+                };
+            }
+        }
+        Err(Error::NotFound(name.to_string()))
+    }
+
+    /// Resolves files, included by `source` directly or through other files,
+    /// in order they should be processed: every file precedes files which include it.
+    /// `path` is the file of `source`, relative includes of other sources are searched in current directory.
+    /// Files are included once, even if several files include them.
+    pub fn resolve(&self, source: &str, path: Option<&Path>) -> Result<Vec<Include>> {
+        let mut stack = vec![];
+        let mut dir = PathBuf::from(".");
+        if let Some(path) = path {
+            stack.push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+            if let Some(parent) = path.parent() {
+                dir = parent.to_path_buf();
+            }
+        }
+        let mut includes = vec![];
+        self.visit(source, &dir, &mut stack, &mut includes)?;
+        Ok(includes)
+    }
+
+    fn visit(
+        &self,
+        source: &str,
+        dir: &Path,
+        stack: &mut Vec<PathBuf>,
+        includes: &mut Vec<Include>,
+    ) -> Result<()> {
+        for (_, name) in statements(source) {
+            let include = self.find(name, dir)?;
+            if let Some(pos) = stack.iter().position(|path| *path == include.path) {
+                let mut cycle = stack[pos..].to_vec();
+                cycle.push(include.path);
+                return Err(Error::Cycle(cycle));
+            }
+            if includes.iter().any(|other| other.path == include.path) {
+                continue;
+            }
+
+            let dir = include.path.parent().unwrap_or(dir).to_path_buf();
+            stack.push(include.path.clone());
+            self.visit(&include.source, &dir, stack, includes)?;
+            stack.pop();
+            includes.push(include);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        let dir = std::env::temp_dir().join(format!("qvnt-i-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(
+            dir.join("main.qasm"),
+            "OPENQASM 2.0;\ninclude \"qelib1.inc\";\ninclude \"lib/a.inc\"; // a\n\
+            // include \"missing.inc\";\ninclude \"b.inc\";\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib/a.inc"), "include \"b.inc\";\n").unwrap();
+        std::fs::write(dir.join("lib/b.inc"), "gate b q { x q; }\n").unwrap();
+        std::fs::write(dir.join("b.inc"), "include \"main.qasm\";\n").unwrap();
+
+        let main = dir.join("main.qasm");
+        let source = std::fs::read_to_string(&main).unwrap();
+        let names: Vec<_> = statements(&source)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(names, ["qelib1.inc", "lib/a.inc", "b.inc"]);
+
+        let canonical = |path: &str| dir.join(path).canonicalize().unwrap();
+        assert_eq!(
+            SearchPath::default().resolve(&source, Some(&main)),
+            Err(Error::Cycle(vec![
+                canonical("main.qasm"),
+                canonical("b.inc"),
+                canonical("main.qasm")
+            ]))
+        );
+
+        std::fs::write(dir.join("b.inc"), "gate b2 q { x q; }\n").unwrap();
+        let paths: Vec<_> = SearchPath::default()
+            .resolve(&source, Some(&main))
+            .unwrap()
+            .into_iter()
+            .map(|include| include.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from(QELIB1),
+                canonical("lib/b.inc"),
+                canonical("lib/a.inc"),
+                canonical("b.inc")
+            ]
+        );
+
+        assert_eq!(
+            SearchPath::default().resolve("include \"b.inc\";", None),
+            Err(Error::NotFound("b.inc".to_string()))
+        );
+        let search = SearchPath {
+            dirs: vec![dir.join("lib")],
+        };
+        assert_eq!(
+            search.resolve("include \"b.inc\";", None).unwrap()[0].path,
+            canonical("lib/b.inc")
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
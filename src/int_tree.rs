@@ -139,6 +139,31 @@ impl<T: utils::drop_leakage::DropExt> Tree<T> {
         }
     }
 
+    pub fn contains<S: AsRef<str>>(&self, tag: S) -> bool {
+        let tag = tag.as_ref().to_string();
+        *self.root == tag || self.map.contains_key(&tag)
+    }
+
+    pub fn path_to<S: AsRef<str>>(&self, tag: S) -> Option<Vec<&T>> {
+        let mut start = Rc::new(tag.as_ref().to_string());
+        let mut path = vec![];
+
+        while start != self.root {
+            let TreeEntry { value, parent } = &self.map.get(&start)?.1;
+
+            path.push(value);
+            start = Rc::clone(parent);
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn path_to_head(&self) -> Vec<&T> {
+        self.path_to(self.head.borrow().as_str())
+            .expect("Head should be reachable from root")
+    }
+
     pub fn remove<S: AsRef<str>>(&mut self, tag: S) -> RemoveStatus {
         let tag = tag.as_ref().to_string();
 
@@ -0,0 +1,295 @@
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownTemplate(String),
+    InvalidSize(Template, usize),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownTemplate(name) => {
+                write!(f, "Unknown template {name:?}, list templates by `lib`")
+            }
+            Error::InvalidSize(template, size) => write!(
+                f,
+                "Template {template} is defined for sizes from {} to {}, not {size}",
+                template.min_size(),
+                template.max_size()
+            ),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Parametrised gate definition, which is sized to a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    Qft,
+    InverseQft,
+    GroverDiffuser,
+    Mcx,
+    Adder,
+    Ghz,
+}
+
+/// Gate application in template body: gate name, argument and indices of qubits.
+type Op = (String, Option<String>, Vec<usize>);
+
+impl Template {
+    pub const ALL: [Template; 6] = [
+        Template::Qft,
+        Template::InverseQft,
+        Template::GroverDiffuser,
+        Template::Mcx,
+        Template::Adder,
+        Template::Ghz,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Template::Qft => "qft",
+            Template::InverseQft => "iqft",
+            Template::GroverDiffuser => "grover-diffuser",
+            Template::Mcx => "mcx",
+            Template::Adder => "adder",
+            Template::Ghz => "ghz",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Template::Qft => "Quantum Fourier transform, the first qubit is the least significant",
+            Template::InverseQft => "Inverse quantum Fourier transform",
+            Template::GroverDiffuser => "Grover diffusion operator, reflection about |+...+>",
+            Template::Mcx => "X on the last qubit, controlled by the rest of them",
+            Template::Adder => {
+                "Ripple-carry adder b += a of Cuccaro et al, on N-qubit a and b with carries cin and cout"
+            }
+            Template::Ghz => "Preparation of GHZ state from |0...0>",
+        }
+    }
+
+    fn min_size(&self) -> usize {
+        match self {
+            Template::Mcx => 2,
+            _ => 1,
+        }
+    }
+
+    /// Maximal size, for which all qubits of the gate fit into register masks.
+    fn max_size(&self) -> usize {
+        match self {
+            Template::Adder => (usize::BITS as usize - 2) / 2,
+            _ => usize::BITS as usize,
+        }
+    }
+
+    /// Name of the gate, defined by the template of `size`, e.g. `qft4`.
+    pub fn gate_name(&self, size: usize) -> String {
+        format!("{}{size}", self.name().replace('-', "_"))
+    }
+
+    fn qubits(&self, size: usize) -> Vec<String> {
+        match self {
+            Template::Adder => std::iter::once("cin".to_string())
+                .chain((0..size).map(|i| format!("a{i}")))
+                .chain((0..size).map(|i| format!("b{i}")))
+                .chain(std::iter::once("cout".to_string()))
+                .collect(),
+            _ => (0..size).map(|i| format!("q{i}")).collect(),
+        }
+    }
+
+    fn body(&self, n: usize) -> Vec<Op> {
+        let op = |name: &str, regs: Vec<usize>| (name.to_string(), None, regs);
+        match self {
+            Template::Qft => {
+                let mut ops = vec![];
+                for j in (0..n).rev() {
+                    ops.push(op("h", vec![j]));
+                    for k in (0..j).rev() {
+                        let angle = format!("pi/{}", 1u64 << (j - k));
+                        ops.push(("cu1".to_string(), Some(angle), vec![k, j]));
+                    }
+                }
+                for i in 0..n / 2 {
+                    ops.push(op("swap", vec![i, n - 1 - i]));
+                }
+                ops
+            }
+            Template::InverseQft => Template::Qft
+                .body(n)
+                .into_iter()
+                .rev()
+                .map(|(name, arg, regs)| (name, arg.map(|arg| format!("-{arg}")), regs))
+                .collect(),
+            Template::GroverDiffuser => {
+                let all = |name: &'static str| (0..n).map(move |i| op(name, vec![i]));
+                let mcx = format!("{}x", "c".repeat(n - 1));
+                all("h")
+                    .chain(all("x"))
+                    .chain([
+                        op("h", vec![n - 1]),
+                        op(&mcx, (0..n).collect()),
+                        op("h", vec![n - 1]),
+                    ])
+                    .chain(all("x"))
+                    .chain(all("h"))
+                    .collect()
+            }
+            Template::Mcx => vec![op(&format!("{}x", "c".repeat(n - 1)), (0..n).collect())],
+            Template::Adder => {
+                let (cin, a, b, cout) = (0, |i| 1 + i, |i| 1 + n + i, 2 * n + 1);
+                let majority = |x, y, z| {
+                    [
+                        op("cx", vec![z, y]),
+                        op("cx", vec![z, x]),
+                        op("ccx", vec![x, y, z]),
+                    ]
+                };
+                let unmaj = |x, y, z| {
+                    [
+                        op("ccx", vec![x, y, z]),
+                        op("cx", vec![z, x]),
+                        op("cx", vec![x, y]),
+                    ]
+                };
+
+                let mut ops = majority(cin, b(0), a(0)).to_vec();
+                for i in 1..n {
+                    ops.extend(majority(a(i - 1), b(i), a(i)));
+                }
+                ops.push(op("cx", vec![a(n - 1), cout]));
+                for i in (1..n).rev() {
+                    ops.extend(unmaj(a(i - 1), b(i), a(i)));
+                }
+                ops.extend(unmaj(cin, b(0), a(0)));
+                ops
+            }
+            Template::Ghz => std::iter::once(op("h", vec![0]))
+                .chain((1..n).map(|i| op("cx", vec![i - 1, i])))
+                .collect(),
+        }
+    }
+
+    /// Gate name with its qubits, e.g. `ghz2 q0, q1`.
+    pub fn signature(&self, size: usize) -> String {
+        format!("{} {}", self.gate_name(size), self.qubits(size).join(", "))
+    }
+
+    /// Writes the template of `size` as QASM `gate` statement.
+    pub fn define(&self, size: usize) -> Result<String> {
+        if !(self.min_size()..=self.max_size()).contains(&size) {
+            return Err(Error::InvalidSize(*self, size));
+        }
+        let qubits = self.qubits(size);
+        let body: String = self
+            .body(size)
+            .into_iter()
+            .map(|(name, arg, regs)| {
+                let regs: Vec<_> = regs.iter().map(|&reg| qubits[reg].as_str()).collect();
+                match arg {
+                    Some(arg) => format!(" {name}({arg}) {};", regs.join(", ")),
+                    None => format!(" {name} {};", regs.join(", ")),
+                }
+            })
+            .collect();
+        Ok(format!("gate {} {{{body} }}\n", self.signature(size)))
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|template| template.name() == s)
+            .ok_or_else(|| Error::UnknownTemplate(s.to_string()))
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Lists templates with their descriptions.
+pub fn list() -> String {
+    let mut list = "Templates, defined by `lib NAME REG` as gate NAMEN for register REG of N qubits (or for N qubits):\n".to_string();
+    for template in Template::ALL {
+        list += &format!("  {:<16}{}\n", template.name(), template.description());
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuit::circuit,
+        equiv::{self, Verdict},
+        sim::Sim,
+    };
+
+    fn state(source: &str) -> Vec<num_complex::Complex64> {
+        let mut sim = Sim::default();
+        sim.init(circuit(source)).unwrap();
+        sim.finish().state().to_vec()
+    }
+
+    #[test]
+    fn templates() {
+        assert_eq!(
+            Template::Ghz.define(3),
+            Ok("gate ghz3 q0, q1, q2 { h q0; cx q0, q1; cx q1, q2; }\n".to_string())
+        );
+        assert_eq!("grover-diffuser".parse(), Ok(Template::GroverDiffuser));
+        assert_eq!(
+            Template::Mcx.define(1),
+            Err(Error::InvalidSize(Template::Mcx, 1))
+        );
+        assert_eq!(
+            Template::Qft.define(70),
+            Err(Error::InvalidSize(Template::Qft, 70))
+        );
+        assert!(Template::Qft.define(64).is_ok());
+        assert!(Template::Adder.define(32).is_err());
+
+        let psi = state(&format!(
+            "{}qreg q[3]; x q[0]; x q[2]; qft3 q[0], q[1], q[2];",
+            Template::Qft.define(3).unwrap()
+        ));
+        let global_phase = psi[0] / psi[0].norm();
+        for (y, amp) in psi.iter().enumerate() {
+            let phase = 2.0 * std::f64::consts::PI * (5 * y) as f64 / 8.0;
+            let expected = num_complex::Complex64::from_polar(8f64.sqrt().recip(), phase);
+            assert!((amp - expected * global_phase).norm() < 1e-9);
+        }
+
+        let inverse = circuit(&format!(
+            "{}{}qreg q[3]; qft3 q[0], q[1], q[2]; iqft3 q[0], q[1], q[2];",
+            Template::Qft.define(3).unwrap(),
+            Template::InverseQft.define(3).unwrap()
+        ));
+        let identity = circuit("qreg q[3];");
+        assert_eq!(
+            equiv::check(&inverse, &identity).unwrap().verdict,
+            Verdict::Equivalent
+        );
+
+        let psi = state(&format!(
+            "{}qreg cin[1]; qreg a[3]; qreg b[3]; qreg cout[1]; x a[1]; x b[0]; x b[2]; \
+            adder3 cin[0], a[0], a[1], a[2], b[0], b[1], b[2], cout[0];",
+            Template::Adder.define(3).unwrap()
+        ));
+        assert!((psi[(2 << 1) | (7 << 4)].norm() - 1.0).abs() < 1e-9);
+    }
+}
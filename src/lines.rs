@@ -1,11 +1,29 @@
-use std::{fmt, path::PathBuf, str::FromStr};
+use std::{fmt, num::NonZeroUsize, path::PathBuf, str::FromStr};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     UnknownCommand(String),
     UnspecifiedPath,
     UnspecifiedInt,
+    UnspecifiedShots,
+    UnspecifiedCircuits,
+    UnspecifiedExport,
+    UnspecifiedInit,
+    UnspecifiedBasis,
+    UnspecifiedSweep,
+    UnspecifiedLet,
+    UnspecifiedMinimize,
+    UnspecifiedNoise,
+    UnspecifiedLib,
+    InvalidLoopCount(String),
+    InvalidSetting(String),
     Tag(crate::int_tree::Error),
+    Transpile(crate::transpile::Error),
+    Debug(crate::debug::Error),
+    Watch(crate::watch::Error),
+    Assertion(crate::assertion::Error),
+    Library(crate::library::Error),
+    Observable(crate::observable::Error),
     ExistedTagName(String),
     TagIsParent(String),
     TagIsHead(String),
@@ -17,9 +35,65 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::UnknownCommand(s) => write!(f, "Unknown command: {s}"),
-            Error::UnspecifiedPath => write!(f, "Path to load file must be specified"),
+            Error::UnspecifiedPath => write!(f, "Path to file must be specified"),
             Error::UnspecifiedInt => write!(f, "Integer must be specified for loop"),
+            Error::UnspecifiedShots => write!(f, "Number of shots must be specified for sample"),
+            Error::UnspecifiedCircuits => {
+                write!(f, "Two tags or files must be specified to compare")
+            }
+            Error::UnspecifiedExport => {
+                write!(f, "Export format (latex|svg) and file must be specified")
+            }
+            Error::UnspecifiedInit => {
+                write!(f, "Registers must be specified for init as REG=VALUE")
+            }
+            Error::UnspecifiedBasis => {
+                write!(
+                    f,
+                    "Basis must be specified as comma-separated gates, e.g. `cx,rz,sx`"
+                )
+            }
+            Error::UnspecifiedSweep => {
+                write!(
+                    f,
+                    "Sweep must be specified as VAR FROM TO STEPS [VAR FROM TO STEPS] EXPR"
+                )
+            }
+            Error::UnspecifiedLet => {
+                write!(f, "Variable must be specified as NAME = EXPR")
+            }
+            Error::UnspecifiedMinimize => {
+                write!(
+                    f,
+                    "Minimisation must be specified as \"OBSERVABLE\" over VAR,... [from VALUE,...] [method nelder-mead|spsa]"
+                )
+            }
+            Error::UnspecifiedNoise => {
+                write!(
+                    f,
+                    "Noise must be specified as CHANNEL [TARGET] P with probability P from 0 to 1, \
+                    where CHANNEL is depolarizing|bitflip|readout|amplitude-damping, or as `off`"
+                )
+            }
+            Error::UnspecifiedLib => {
+                write!(
+                    f,
+                    "Template and register must be specified as NAME REG, or NAME N for N qubits"
+                )
+            }
+            Error::InvalidLoopCount(s) => {
+                write!(f, "Loop count must be a non-negative integer: {s}")
+            }
+            Error::InvalidSetting(s) => {
+                write!(f, "Unknown setting or invalid value: {s}")
+            }
             Error::Tag(e) => write!(f, "Tag error: {e}"),
+            Error::Transpile(e) => write!(f, "{e}"),
+            Error::Debug(e) => write!(f, "Debugger error: {e}"),
+            Error::Watch(e) => write!(f, "{e}"),
+            Error::Assertion(e) => write!(f, "{e}"),
+            Error::Library(e) => write!(f, "{e}"),
+            Error::Observable(e) => write!(f, "{e}"),
             Error::ExistedTagName(s) => write!(f, "Tag name {s:?} already exists"),
             Error::TagIsParent(s) => write!(f, "Tag {s:?} is parent and cannot be removed"),
             Error::TagIsHead(s) => write!(f, "Tag {s:?} is head and cannot be removed"),
@@ -40,7 +114,72 @@ COMMANDS:
     load FILE   Load state from FILE according to QASM language script
     tag TAGCMD  Interact with tags, more about by `tag ?`
     go|g        Start modulating quantum computer
-    loop|l N    Repeat following commands N time
+    sample N    Run the circuit N times, showing counts of classical register values; if measurements
+                are only at the end, the circuit is simulated once and outcomes are sampled
+    debug       Step through the circuit operation by operation, more about by `help` in debugger
+    watch-expr [\"EXPR\"|clear]
+                Evaluate EXPR after each operation of traced run: `prob QUBIT` for probability of |1>
+                or `expect PAULIS` (e.g. `Z0 Z1`) for expectation value; list or remove expressions
+    trace [-o FILE]
+                Run the circuit from the start, showing watched expressions after each operation
+                as a table or saving them to FILE as CSV
+    sweep VAR FROM TO STEPS [VAR FROM TO STEPS] [-o FILE] EXPR
+                Simulate the circuit for STEPS values of parameter VAR from FROM to TO (one or two
+                parameters), showing watch expression EXPR (e.g. `prob q[0]`) for every value
+                as a table or saving it to FILE as CSV; EXPR takes the rest of the line.
+                VARs must be used in gate arguments, so they are set by `let` before the lines with them
+    assert prob REG=VALUE P [±TOL]
+                Check probability of qubits REG (e.g. `q` or `q[0]`) to be measured as VALUE
+    assert class REG ==|!= VALUE
+                Check value of classical bits REG after the last run
+    assert dist REG ~ {VALUE:P, ...} [p>ALPHA] [shots N]
+                Run the circuit N (1000) times and check measured values of classical bits REG follow
+                the distribution by G-test at significance ALPHA (0.01); failed assertions make
+                batch mode exit with non-zero code
+    time CMDS   Report time spent on parsing, building, initialising and simulation
+    profile     Simulate the circuit, reporting time spent on each kind of operation
+    noise CHANNEL [TARGET] P|off
+                Inject errors with probability P on every run: `depolarizing`, `bitflip` or
+                `amplitude-damping` after gates, measurements and resets, `readout` on recorded bits,
+                on TARGET qubits (e.g. `q[2]`) or all of them; `off` returns to ideal simulation
+    seed [N]    Restart measurement sampling from seed N or show the current seed
+    set threads N
+                Set number of simulation threads, 1 for single-threaded mode
+    status      Show register sizes and simulation settings
+    equiv A B   Check circuits A and B (tags or files) are equal up to global phase
+    draw [TAG]  Draw current circuit or circuit of TAG as a text diagram
+    lint        Report suspicious places of current program: unused qubits, bits and gates,
+                overwritten measurements, gates after final measurements and literals like `3.14`
+    resources [TAG]
+                Show gate counts, depth, memory and runtime estimates of current circuit or TAG
+    optimize [TAG] [--commit NEWTAG]
+                Cancel inverse gates, merge rotations and drop identities in current circuit or TAG,
+                showing gate counts before and after; `--commit` saves the result as NEWTAG
+    transpile [--basis GATES] [TAG] [-o FILE]
+                Decompose current circuit or TAG into comma-separated GATES (`cx,rz,sx` by default),
+                print or save it as QASM and verify the result by simulation
+    export FMT FILE [TAG]
+                Export current circuit or circuit of TAG to FILE as `latex` (quantikz) or `svg`
+    init REG=VALUE...
+                Set quantum registers to bit string (MSB first), decimal integer, `+` or `-`
+    save-state FILE
+                Save state of quantum register to FILE as NumPy .npy array (or CSV for *.csv)
+    load-state [FILE]
+                Start quantum register from normalised state in FILE (.npy or CSV with `re,im` lines),
+                or from |0> if FILE is omitted
+    let NAME = EXPR
+                Set session variable NAME to EXPR, which is usable in gate arguments and loop counts;
+                lines with unset variables in gate arguments are rejected
+    vars        Show session variables
+    lib [NAME REG]
+                Define gate of template NAME (`qft`, `grover-diffuser`, etc.) for qubits of register REG
+                (or for REG qubits, if it's a number) in current state, or list templates
+    minimize \"OBSERVABLE\" over VAR,... [from VALUE,...] [method nelder-mead|spsa]
+                Minimise expectation value of Pauli OBSERVABLE (e.g. `Z0 Z1 - 0.5*X0`) over circuit
+                parameters VARs, printing convergence trace and binding the best values as variables
+                VARs are set by `let` before the lines with them, search starts from their values
+                unless `from` is given
+    loop|l N    Repeat following commands N time, N is an integer or an expression of variables
     class|c     Show state of classical registers
     polar       Show state of quantum registers in polar form
     prob|p      Show state of quantum registers in probability form
@@ -51,11 +190,43 @@ COMMANDS:
 ";
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Threads(NonZeroUsize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Command {
-    Loop(usize),
+    Loop(String),
+    Let(String, String),
+    Vars,
+    Minimize(crate::minimize::Minimize),
     Tags(crate::int_tree::Command),
     Go,
+    Sample(usize),
+    Debug,
+    Watch(Option<crate::watch::Watch>),
+    Unwatch,
+    Trace(Option<PathBuf>),
+    Sweep(crate::sweep::Sweep, Option<PathBuf>),
+    Assert(crate::assertion::Assertion),
+    Time,
+    Profile,
+    Noise(Option<crate::noise::Rule>),
+    Lib(Option<(crate::library::Template, String)>),
+    Seed(Option<u64>),
+    Set(Setting),
+    Status,
+    Equiv(String, String),
+    Draw(Option<String>),
+    Lint,
+    Resources(Option<String>),
+    Optimize(Option<String>, Option<String>),
+    Transpile(crate::transpile::Basis, Option<String>, Option<PathBuf>),
+    Export(crate::export::Format, PathBuf, Option<String>),
     Load(PathBuf),
+    Init(Vec<(String, String)>),
+    SaveState(PathBuf),
+    LoadState(Option<PathBuf>),
     Class,
     Polar,
     Probs,
@@ -65,27 +236,95 @@ pub enum Command {
     Quit,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Line {
     Commands(Vec<Command>),
     Qasm,
 }
 
 impl Line {
-    fn parse_command<'a, I: Iterator<Item = &'a str>>(
-        mut source: I,
-    ) -> Result<Vec<Command>, Error> {
+    fn is_command(token: &str) -> bool {
+        !matches!(
+            Self::parse_command(std::iter::once(token)),
+            Err(Error::UnknownCommand(_))
+        )
+    }
+
+    fn parse_command<'a, I: Iterator<Item = &'a str>>(source: I) -> Result<Vec<Command>, Error> {
+        let mut source = source.peekable();
         let size_hint = source.size_hint();
         let mut cmds = Vec::with_capacity(size_hint.1.unwrap_or(size_hint.0));
 
         while let Some(cmd) = source.next() {
             match cmd {
                 "loop" | "l" => {
-                    let int = source
+                    let int = source.next().ok_or(Error::UnspecifiedInt)?;
+                    cmds.push(Command::Loop(int.to_string()));
+                }
+                "let" => {
+                    let name = source.next().ok_or(Error::UnspecifiedLet)?;
+                    let (name, expr) = match name.split_once('=') {
+                        Some((name, "")) => (name, source.next()),
+                        Some((name, expr)) => (name, Some(expr)),
+                        None => match source.next() {
+                            Some("=") => (name, source.next()),
+                            Some(expr) => (name, expr.strip_prefix('=')),
+                            None => (name, None),
+                        },
+                    };
+                    match expr {
+                        Some(expr) if crate::params::is_var(name) && !expr.is_empty() => {
+                            cmds.push(Command::Let(name.to_string(), expr.to_string()))
+                        }
+                        _ => return Err(Error::UnspecifiedLet),
+                    }
+                }
+                "minimize" => {
+                    let mut obs = vec![];
+                    while let Some(token) = source.next_if(|token| *token != "over") {
+                        obs.push(token);
+                    }
+                    let obs = match (obs.is_empty(), source.next()) {
+                        (false, Some("over")) => {
+                            obs.join(" ").parse().map_err(Error::Observable)?
+                        }
+                        _ => return Err(Error::UnspecifiedMinimize),
+                    };
+                    let vars: Vec<_> = source
                         .next()
-                        .and_then(|int| int.parse().ok())
-                        .ok_or(Error::UnspecifiedInt)?;
-                    cmds.push(Command::Loop(int));
+                        .ok_or(Error::UnspecifiedMinimize)?
+                        .split(',')
+                        .map(str::to_string)
+                        .collect();
+                    if !vars.iter().all(|var| crate::params::is_var(var)) {
+                        return Err(Error::UnspecifiedMinimize);
+                    }
+
+                    let (mut from, mut method) = (None, Default::default());
+                    while let Some(arg) = source.next_if(|arg| matches!(*arg, "from" | "method")) {
+                        let value = source.next().ok_or(Error::UnspecifiedMinimize)?;
+                        match arg {
+                            "from" => {
+                                let values = value
+                                    .split(',')
+                                    .map(|value| crate::circuit::eval(value, None).ok())
+                                    .collect::<Option<Vec<_>>>()
+                                    .filter(|values| values.len() == vars.len())
+                                    .ok_or(Error::UnspecifiedMinimize)?;
+                                from = Some(values);
+                            }
+                            _ => method = value.parse().map_err(|_| Error::UnspecifiedMinimize)?,
+                        }
+                    }
+                    cmds.push(Command::Minimize(crate::minimize::Minimize {
+                        obs,
+                        vars,
+                        from,
+                        method,
+                    }));
+                }
+                "vars" => {
+                    cmds.push(Command::Vars);
                 }
                 "tag" => {
                     cmds.push(Command::Tags(crate::int_tree::Command::parse_command(
@@ -111,6 +350,195 @@ impl Line {
                 "go" | "g" => {
                     cmds.push(Command::Go);
                 }
+                "sample" => {
+                    let shots = source
+                        .next()
+                        .and_then(|shots| shots.parse().ok())
+                        .ok_or(Error::UnspecifiedShots)?;
+                    cmds.push(Command::Sample(shots));
+                }
+                "debug" => {
+                    cmds.push(Command::Debug);
+                }
+                "watch-expr" => match source.next_if(|expr| !Self::is_command(expr)) {
+                    Some("clear") => cmds.push(Command::Unwatch),
+                    Some(expr) => {
+                        cmds.push(Command::Watch(Some(expr.parse().map_err(Error::Watch)?)))
+                    }
+                    None => cmds.push(Command::Watch(None)),
+                },
+                "trace" => {
+                    let path = match source.next_if(|arg| matches!(*arg, "--output" | "-o")) {
+                        Some(_) => Some(
+                            source
+                                .next()
+                                .and_then(|path| path.parse().ok())
+                                .ok_or(Error::UnspecifiedPath)?,
+                        ),
+                        None => None,
+                    };
+                    cmds.push(Command::Trace(path));
+                }
+                "sweep" => {
+                    let (mut ranges, mut output) = (vec![], None);
+                    let watch = loop {
+                        match source.next() {
+                            Some("--output" | "-o") => {
+                                let path = source
+                                    .next()
+                                    .and_then(|path| path.parse().ok())
+                                    .ok_or(Error::UnspecifiedPath)?;
+                                output = Some(path);
+                            }
+                            Some(arg)
+                                if arg.starts_with(':')
+                                    || arg.contains(' ')
+                                    || matches!(arg, "prob" | "expect") =>
+                            {
+                                let expr: Vec<_> =
+                                    std::iter::once(arg).chain(&mut source).collect();
+                                break expr.join(" ").trim_start_matches(':').parse()?;
+                            }
+                            Some(var) => {
+                                let range = source
+                                    .next()
+                                    .zip(source.next())
+                                    .zip(source.next())
+                                    .and_then(|((from, to), steps)| {
+                                        crate::sweep::Range::parse(var, from, to, steps)
+                                    })
+                                    .ok_or(Error::UnspecifiedSweep)?;
+                                ranges.push(range);
+                            }
+                            None => return Err(Error::UnspecifiedSweep),
+                        }
+                    };
+                    if ranges.is_empty() || ranges.len() > 2 {
+                        return Err(Error::UnspecifiedSweep);
+                    }
+                    cmds.push(Command::Sweep(
+                        crate::sweep::Sweep { ranges, watch },
+                        output,
+                    ));
+                }
+                "assert" => {
+                    let assertion = crate::assertion::Assertion::parse(&mut source)?;
+                    cmds.push(Command::Assert(assertion));
+                }
+                "time" => {
+                    cmds.push(Command::Time);
+                }
+                "profile" => {
+                    cmds.push(Command::Profile);
+                }
+                "noise" => match source.next().ok_or(Error::UnspecifiedNoise)? {
+                    "off" => cmds.push(Command::Noise(None)),
+                    channel => {
+                        let arg = source.next().ok_or(Error::UnspecifiedNoise)?;
+                        let rule = match source.next_if(|prob| prob.parse::<f64>().is_ok()) {
+                            Some(prob) => crate::noise::Rule::parse(channel, Some(arg), prob),
+                            None => crate::noise::Rule::parse(channel, None, arg),
+                        };
+                        cmds.push(Command::Noise(Some(rule.ok_or(Error::UnspecifiedNoise)?)));
+                    }
+                },
+                "seed" => {
+                    let seed = source
+                        .next_if(|seed| seed.parse::<u64>().is_ok())
+                        .and_then(|seed| seed.parse().ok());
+                    cmds.push(Command::Seed(seed));
+                }
+                "set" => {
+                    let setting = match source.next() {
+                        Some("threads") => source
+                            .next()
+                            .and_then(|threads| threads.parse().ok())
+                            .map(Setting::Threads),
+                        _ => None,
+                    };
+                    cmds.push(Command::Set(setting.ok_or_else(|| {
+                        Error::InvalidSetting("expected `threads N` with positive N".to_string())
+                    })?));
+                }
+                "status" => {
+                    cmds.push(Command::Status);
+                }
+                "equiv" => {
+                    let (lhs, rhs) = source
+                        .next()
+                        .zip(source.next())
+                        .ok_or(Error::UnspecifiedCircuits)?;
+                    cmds.push(Command::Equiv(lhs.to_string(), rhs.to_string()));
+                }
+                "draw" => {
+                    let tag = source.next_if(|tag| !Self::is_command(tag));
+                    cmds.push(Command::Draw(tag.map(str::to_string)));
+                }
+                "lint" => {
+                    cmds.push(Command::Lint);
+                }
+                "lib" => match source.next_if(|name| !Self::is_command(name)) {
+                    Some(name) => {
+                        let template = name.parse().map_err(Error::Library)?;
+                        let target = source.next().ok_or(Error::UnspecifiedLib)?;
+                        cmds.push(Command::Lib(Some((template, target.to_string()))));
+                    }
+                    None => cmds.push(Command::Lib(None)),
+                },
+                "resources" | "res" => {
+                    let tag = source.next_if(|tag| !Self::is_command(tag));
+                    cmds.push(Command::Resources(tag.map(str::to_string)));
+                }
+                "optimize" | "opt" => {
+                    let (mut tag, mut commit) = (None, None);
+                    while let Some(arg) = source.next_if(|arg| !Self::is_command(arg)) {
+                        match arg {
+                            "--commit" => {
+                                let new_tag = source
+                                    .next()
+                                    .ok_or(crate::int_tree::Error::UnspecifiedTag)?;
+                                commit = Some(new_tag.to_string());
+                            }
+                            arg if tag.is_none() => tag = Some(arg.to_string()),
+                            arg => return Err(Error::UnknownCommand(arg.to_string())),
+                        }
+                    }
+                    cmds.push(Command::Optimize(tag, commit));
+                }
+                "transpile" => {
+                    let mut basis = crate::transpile::DEFAULT_BASIS.parse().unwrap_or_default();
+                    let (mut tag, mut output) = (None, None);
+                    while let Some(arg) = source.next_if(|arg| !Self::is_command(arg)) {
+                        match arg {
+                            "--basis" => {
+                                basis = source
+                                    .next()
+                                    .ok_or(Error::UnspecifiedBasis)?
+                                    .parse()
+                                    .map_err(Error::Transpile)?;
+                            }
+                            "--output" | "-o" => {
+                                let path = source
+                                    .next()
+                                    .and_then(|path| path.parse().ok())
+                                    .ok_or(Error::UnspecifiedPath)?;
+                                output = Some(path);
+                            }
+                            arg if tag.is_none() => tag = Some(arg.to_string()),
+                            arg => return Err(Error::UnknownCommand(arg.to_string())),
+                        }
+                    }
+                    cmds.push(Command::Transpile(basis, tag, output));
+                }
+                "export" => {
+                    let (format, path) = source
+                        .next()
+                        .and_then(|format| format.parse().ok())
+                        .zip(source.next().and_then(|path| path.parse().ok()))
+                        .ok_or(Error::UnspecifiedExport)?;
+                    let tag = source.next_if(|tag| !Self::is_command(tag));
+                    cmds.push(Command::Export(format, path, tag.map(str::to_string)));
+                }
                 "names" | "n" => {
                     cmds.push(Command::Names);
                 }
@@ -121,6 +549,30 @@ impl Line {
                         .ok_or(Error::UnspecifiedPath)?;
                     cmds.push(Command::Load(path));
                 }
+                "init" => {
+                    let mut regs = vec![];
+                    while let Some(reg) = source.next_if(|reg| reg.contains('=')) {
+                        let (name, value) = reg.split_once('=').ok_or(Error::UnspecifiedInit)?;
+                        regs.push((name.to_string(), value.to_string()));
+                    }
+                    if regs.is_empty() {
+                        return Err(Error::UnspecifiedInit);
+                    }
+                    cmds.push(Command::Init(regs));
+                }
+                "save-state" => {
+                    let path = source
+                        .next()
+                        .and_then(|path| path.parse().ok())
+                        .ok_or(Error::UnspecifiedPath)?;
+                    cmds.push(Command::SaveState(path));
+                }
+                "load-state" => {
+                    let path = source
+                        .next_if(|path| !Self::is_command(path))
+                        .and_then(|path| path.parse().ok());
+                    cmds.push(Command::LoadState(path));
+                }
                 "help" | "h" | "?" => {
                     cmds.push(Command::Help);
                 }
@@ -132,13 +584,30 @@ impl Line {
     }
 }
 
+/// Splits the line by whitespaces, keeping text in double quotes as a single token without quotes.
+fn tokens(mut source: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        source = source.trim_start();
+        let (token, rest) = match source.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None if source.is_empty() => return None,
+            None => source.split_at(
+                source
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(source.len()),
+            ),
+        };
+        source = rest;
+        Some(token)
+    })
+}
+
 impl FromStr for Line {
     type Err = Error;
 
     fn from_str(source: &str) -> Result<Self, Error> {
         if let Some((_, ':')) = source.char_indices().next() {
-            let source = source.split_at(1).1.split_ascii_whitespace();
-            Line::parse_command(source).map(Line::Commands)
+            Line::parse_command(tokens(source.split_at(1).1)).map(Line::Commands)
         } else {
             Ok(Line::Qasm)
         }
@@ -149,6 +618,150 @@ impl FromStr for Line {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_init() {
+        assert_eq!(
+            ":init q=0101 r=+ go".parse::<Line>(),
+            Ok(Line::Commands(vec![
+                Command::Init(vec![
+                    ("q".to_string(), "0101".to_string()),
+                    ("r".to_string(), "+".to_string()),
+                ]),
+                Command::Go,
+            ]))
+        );
+        assert_eq!(":init".parse::<Line>(), Err(Error::UnspecifiedInit));
+    }
+
+    #[test]
+    fn parse_optimize() {
+        assert_eq!(
+            ":optimize adder --commit adder-opt draw".parse::<Line>(),
+            Ok(Line::Commands(vec![
+                Command::Optimize(Some("adder".to_string()), Some("adder-opt".to_string())),
+                Command::Draw(None),
+            ]))
+        );
+        assert_eq!(
+            ":opt".parse::<Line>(),
+            Ok(Line::Commands(vec![Command::Optimize(None, None)]))
+        );
+    }
+
+    #[test]
+    fn parse_watch() {
+        assert_eq!(
+            r#":watch-expr "prob q[0]" watch-expr "expect Z0  Z1" trace -o out.csv"#
+                .parse::<Line>(),
+            Ok(Line::Commands(vec![
+                Command::Watch(Some("prob q[0]".parse().unwrap())),
+                Command::Watch(Some("expect Z0 Z1".parse().unwrap())),
+                Command::Trace(Some("out.csv".into())),
+            ]))
+        );
+        assert_eq!(
+            ":watch-expr clear watch-expr".parse::<Line>(),
+            Ok(Line::Commands(vec![Command::Unwatch, Command::Watch(None)]))
+        );
+    }
+
+    #[test]
+    fn parse_sweep() {
+        let line = ":sweep theta 0 pi 50 phi -1 1 3 -o out.csv :prob q[0]".parse::<Line>();
+        let cmds = match line {
+            Ok(Line::Commands(cmds)) => cmds,
+            line => panic!("unexpected {:?}", line),
+        };
+        match cmds.as_slice() {
+            [Command::Sweep(sweep, Some(path))] => {
+                assert_eq!(sweep.ranges.len(), 2);
+                assert_eq!(sweep.ranges[0].to, std::f64::consts::PI);
+                assert_eq!(sweep.ranges[1].steps, 3);
+                assert_eq!(sweep.watch.to_string(), "prob q[0]");
+                assert_eq!(path, &PathBuf::from("out.csv"));
+            }
+            cmds => panic!("unexpected {:?}", cmds),
+        }
+        assert_eq!(
+            ":sweep theta 0 pi".parse::<Line>(),
+            Err(Error::UnspecifiedSweep)
+        );
+    }
+
+    #[test]
+    fn parse_let() {
+        let let_cmd = |name: &str, expr: &str| Command::Let(name.to_string(), expr.to_string());
+        assert_eq!(
+            ":let theta = pi/3 let n=4 let m= n*2 let k =1 loop n vars".parse::<Line>(),
+            Ok(Line::Commands(vec![
+                let_cmd("theta", "pi/3"),
+                let_cmd("n", "4"),
+                let_cmd("m", "n*2"),
+                let_cmd("k", "1"),
+                Command::Loop("n".to_string()),
+                Command::Vars,
+            ]))
+        );
+        assert_eq!(":let 2x = 1".parse::<Line>(), Err(Error::UnspecifiedLet));
+        assert_eq!(":let x =".parse::<Line>(), Err(Error::UnspecifiedLet));
+    }
+
+    #[test]
+    fn parse_minimize() {
+        use crate::minimize::{Method, Minimize};
+
+        assert_eq!(
+            r#":minimize "Z0 Z1 - 0.5*X0" over theta,phi from 0.1,pi/2 method spsa vars"#
+                .parse::<Line>(),
+            Ok(Line::Commands(vec![
+                Command::Minimize(Minimize {
+                    obs: "Z0 Z1 - 0.5*X0".parse().unwrap(),
+                    vars: vec!["theta".to_string(), "phi".to_string()],
+                    from: Some(vec![0.1, std::f64::consts::FRAC_PI_2]),
+                    method: Method::Spsa,
+                }),
+                Command::Vars,
+            ]))
+        );
+        assert_eq!(
+            ":minimize Z0 over theta from 1,2".parse::<Line>(),
+            Err(Error::UnspecifiedMinimize)
+        );
+    }
+
+    #[test]
+    fn parse_noise() {
+        use crate::noise::Rule;
+
+        assert_eq!(
+            ":noise bitflip q[2] 0.05 noise readout 0.02 noise off".parse::<Line>(),
+            Ok(Line::Commands(vec![
+                Command::Noise(Rule::parse("bitflip", Some("q[2]"), "0.05")),
+                Command::Noise(Rule::parse("readout", None, "0.02")),
+                Command::Noise(None),
+            ]))
+        );
+        assert_eq!(
+            ":noise depolarizing".parse::<Line>(),
+            Err(Error::UnspecifiedNoise)
+        );
+    }
+
+    #[test]
+    fn parse_lib() {
+        use crate::library::Template;
+
+        assert_eq!(
+            ":lib qft q lib lib mcx 3".parse::<Line>(),
+            Ok(Line::Commands(vec![
+                Command::Lib(Some((Template::Qft, "q".to_string()))),
+                Command::Lib(None),
+                Command::Lib(Some((Template::Mcx, "3".to_string()))),
+            ]))
+        );
+        assert_eq!(":lib ghz".parse::<Line>(), Err(Error::UnspecifiedLib));
+    }
+
     #[test]
     fn parse_cmd() {
         assert_eq!("a b c d".parse::<Line>(), Ok(Line::Qasm));
@@ -0,0 +1,342 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use qasm::{Argument, AstNode};
+use qvnt::qasm::Ast;
+
+use crate::{
+    circuit::{self, Circuit, Register},
+    cli::CliArgs,
+    process,
+    program::{self, ProgramResult},
+};
+
+/// Angles, which literals are suspected to approximate.
+const ANGLES: &[(f64, &str)] = &[
+    (std::f64::consts::PI, "pi"),
+    (std::f64::consts::FRAC_PI_2, "pi/2"),
+    (std::f64::consts::FRAC_PI_3, "pi/3"),
+    (std::f64::consts::FRAC_PI_4, "pi/4"),
+    (std::f64::consts::FRAC_PI_6, "pi/6"),
+    (std::f64::consts::FRAC_PI_8, "pi/8"),
+    (2.0 * std::f64::consts::FRAC_PI_3, "2*pi/3"),
+    (3.0 * std::f64::consts::FRAC_PI_4, "3*pi/4"),
+    (3.0 * std::f64::consts::FRAC_PI_2, "3*pi/2"),
+    (std::f64::consts::TAU, "2*pi"),
+];
+/// Relative difference between the literal and the angle, which is considered suspicious.
+const ANGLE_TOLERANCE: f64 = 2e-3;
+
+/// Position in the source, both line and column start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Location {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: warning: {}", self.location, self.message)
+    }
+}
+
+/// Sources of program lines, numbered one after another.
+struct Sources<'t> {
+    sources: Vec<(&'t str, usize)>,
+}
+
+impl<'t> Sources<'t> {
+    fn locate(&self, slice: &str) -> Location {
+        let ptr = slice.as_ptr() as usize;
+        for (source, first_line) in &self.sources {
+            let start = source.as_ptr() as usize;
+            if (start..=start + source.len()).contains(&ptr) {
+                let before = &source[..ptr - start];
+                let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+                return Location {
+                    line: first_line + before.matches('\n').count(),
+                    col: before[line_start..].chars().count() + 1,
+                };
+            }
+        }
+        Location::default()
+    }
+}
+
+/// Usage of qubits, bits and macros, collected while walking through the program.
+#[derive(Default)]
+struct Linter {
+    circuit: Circuit,
+    q_decls: Vec<Location>,
+    c_decls: Vec<Location>,
+    q_used: usize,
+    c_written: usize,
+    c_read: usize,
+    /// Measurements, which results weren't read yet, by bit index.
+    unread: HashMap<usize, Location>,
+    /// Gates on measured qubits after their last measurement.
+    after_measure: Vec<(Location, String, usize)>,
+    q_measured: usize,
+    macros: Vec<(String, Location)>,
+    used_macros: Vec<String>,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+    fn warn(&mut self, location: Location, message: String) {
+        self.warnings.push(Warning { location, message });
+    }
+
+    fn arg_name<'t>(arg: &Argument<'t>) -> &'t str {
+        match arg {
+            Argument::Qubit(name, _) | Argument::Register(name) => name,
+        }
+    }
+
+    fn check_angles(&mut self, sources: &Sources<'_>, args: &[&str]) {
+        for arg in args {
+            let mut rest = *arg;
+            while let Some(start) = rest.find(|c: char| c.is_ascii_digit() || c == '.') {
+                let len = rest[start..]
+                    .find(|c: char| !c.is_ascii_digit() && c != '.')
+                    .unwrap_or(rest.len() - start);
+                let literal = &rest[start..start + len];
+                rest = &rest[start + len..];
+                let value = match literal.parse::<f64>() {
+                    Ok(value) if literal.contains('.') => value,
+                    _ => continue,
+                };
+                if let Some((_, name)) = ANGLES
+                    .iter()
+                    .find(|(angle, _)| (value - angle).abs() <= angle * ANGLE_TOLERANCE)
+                {
+                    self.warn(
+                        sources.locate(literal),
+                        format!("literal {literal} looks like {name}, use `{name}` instead"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn add_node(&mut self, sources: &Sources<'_>, node: &AstNode<'_>) -> circuit::Result {
+        match node {
+            AstNode::QReg(name, size) => {
+                let reg = Register::new(name, *size as usize, self.circuit.q_num())?;
+                self.circuit.q_regs.push(reg);
+                self.q_decls.push(sources.locate(name));
+            }
+            AstNode::CReg(name, size) => {
+                let reg = Register::new(name, *size as usize, self.circuit.c_num())?;
+                self.circuit.c_regs.push(reg);
+                self.c_decls.push(sources.locate(name));
+            }
+            AstNode::Gate(name, _, _, nodes) => {
+                self.macros.push((name.to_string(), sources.locate(name)));
+                for node in nodes {
+                    if let AstNode::ApplyGate(name, _, args) = node {
+                        self.used_macros.push(name.to_string());
+                        self.check_angles(sources, args);
+                    }
+                }
+            }
+            AstNode::ApplyGate(name, regs, args) => {
+                let mut q_mask = 0;
+                for reg in regs {
+                    q_mask |= self.circuit.q_mask(reg.clone())?;
+                }
+                self.q_used |= q_mask;
+                self.used_macros.push(name.to_string());
+                self.check_angles(sources, args);
+                if q_mask & self.q_measured != 0 {
+                    self.after_measure.push((
+                        sources.locate(name),
+                        name.to_string(),
+                        q_mask & self.q_measured,
+                    ));
+                }
+            }
+            AstNode::Measure(q_arg, c_arg) => {
+                let location = sources.locate(Self::arg_name(q_arg));
+                let q_mask = self.circuit.q_mask(q_arg.clone())?;
+                let c_mask = self.circuit.c_mask(c_arg.clone())?;
+                self.q_used |= q_mask;
+                self.q_measured |= q_mask;
+                self.c_written |= c_mask;
+                for (_, _, mask) in &mut self.after_measure {
+                    *mask &= !q_mask;
+                }
+                self.after_measure.retain(|(_, _, mask)| *mask != 0);
+                for bit in circuit::bits(c_mask) {
+                    if let Some(prev) = self.unread.insert(bit, location) {
+                        let name =
+                            Register::bit_name(&self.circuit.c_regs, bit).unwrap_or_default();
+                        self.warn(
+                            location,
+                            format!("result of measurement at {prev} into {name} is overwritten before use"),
+                        );
+                    }
+                }
+            }
+            AstNode::Reset(arg) => self.q_used |= self.circuit.q_mask(arg.clone())?,
+            AstNode::Barrier(_) | AstNode::Opaque(..) => {}
+            AstNode::If(name, _, node) => {
+                let c_mask = self.circuit.c_mask(Argument::Register(name))?;
+                self.c_read |= c_mask;
+                for bit in circuit::bits(c_mask) {
+                    self.unread.remove(&bit);
+                }
+                self.add_node(sources, node)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<Warning> {
+        for (location, name, mask) in std::mem::take(&mut self.after_measure) {
+            let qubits: Vec<_> = circuit::bits(mask)
+                .filter_map(|bit| Register::bit_name(&self.circuit.q_regs, bit))
+                .collect();
+            self.warn(
+                location,
+                format!(
+                    "gate {name} is applied to {} after the final measurement",
+                    qubits.join(", ")
+                ),
+            );
+        }
+
+        for (reg, location) in self.circuit.q_regs.clone().iter().zip(self.q_decls.clone()) {
+            let unused = reg.mask() & !self.q_used;
+            if unused == reg.mask() {
+                self.warn(
+                    location,
+                    format!("qubits of register {} are never used", reg.name),
+                );
+            } else {
+                for bit in circuit::bits(unused) {
+                    let name = Register::bit_name(&self.circuit.q_regs, bit).unwrap_or_default();
+                    self.warn(location, format!("qubit {name} is never used"));
+                }
+            }
+        }
+
+        for (reg, location) in self.circuit.c_regs.clone().iter().zip(self.c_decls.clone()) {
+            let unused = reg.mask() & !self.c_written & !self.c_read;
+            let unwritten = reg.mask() & !self.c_written & self.c_read;
+            if unused == reg.mask() {
+                self.warn(
+                    location,
+                    format!("bits of register {} are never written or read", reg.name),
+                );
+            } else {
+                for bit in circuit::bits(unused) {
+                    let name = Register::bit_name(&self.circuit.c_regs, bit).unwrap_or_default();
+                    self.warn(location, format!("bit {name} is never written or read"));
+                }
+            }
+            for bit in circuit::bits(unwritten) {
+                let name = Register::bit_name(&self.circuit.c_regs, bit).unwrap_or_default();
+                self.warn(location, format!("bit {name} is read, but never written"));
+            }
+        }
+
+        for (name, location) in std::mem::take(&mut self.macros) {
+            if !self.used_macros.contains(&name) {
+                self.warn(location, format!("gate {name} is defined, but never used"));
+            }
+        }
+
+        self.warnings.sort_by_key(|warning| warning.location);
+        self.warnings
+    }
+}
+
+/// Reports suspicious places of the program, which consists of `asts` in order.
+/// Lines are numbered continuously through sources of all ASTs.
+pub fn lint<'a, 't: 'a>(
+    asts: impl IntoIterator<Item = &'a Ast<'t>>,
+) -> circuit::Result<Vec<Warning>> {
+    let asts: Vec<_> = asts.into_iter().collect();
+    let mut sources = Sources { sources: vec![] };
+    let mut line = 1;
+    for ast in &asts {
+        sources.sources.push((ast.source(), line));
+        line += ast.source().trim_end_matches('\n').matches('\n').count() + 1;
+    }
+
+    let mut linter = Linter::default();
+    for ast in asts {
+        for node in ast.clone() {
+            linter.add_node(&sources, &node)?;
+        }
+    }
+    Ok(linter.finish())
+}
+
+/// Runs the `check` subcommand: builds the program from the file and prints warnings.
+/// Included files are not checked.
+pub fn run(cli: &CliArgs, path: &Path) -> ProgramResult {
+    let mut process = program::new_process(cli)?;
+    let int = process.int_from_file(path.to_path_buf())?;
+
+    let warnings = lint(int.iter_ast().filter(|ast| !process.is_include(ast)))
+        .map_err(process::Error::from)?;
+    for warning in &warnings {
+        println!("{}:{}", path.display(), warning);
+    }
+    println!("{}: {} warnings", path.display(), warnings.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings() {
+        let source = "OPENQASM 2.0;
+qreg q[3];
+qreg r[1];
+creg c[2];
+creg d[1];
+gate unused a { h a; }
+rx(3.14) q[0];
+measure q[0] -> c[0];
+measure q[1] -> c[0];
+h q[0];
+if(c==1) x q[1];
+rz(pi/2) q[1];
+measure q[1] -> c[1];
+";
+        let ast = Ast::from_source(source).unwrap();
+        let warnings: Vec<_> = lint([&ast])
+            .unwrap()
+            .into_iter()
+            .map(|warning| warning.to_string())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "2:6: warning: qubit q[2] is never used",
+                "3:6: warning: qubits of register r are never used",
+                "5:6: warning: bits of register d are never written or read",
+                "6:6: warning: gate unused is defined, but never used",
+                "7:4: warning: literal 3.14 looks like pi, use `pi` instead",
+                "9:9: warning: result of measurement at 8:9 into c[0] is overwritten before use",
+                "10:1: warning: gate h is applied to q[0] after the final measurement",
+            ]
+        );
+    }
+}
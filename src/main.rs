@@ -1,11 +1,42 @@
+mod assertion;
+mod circuit;
 mod cli;
+mod debug;
+mod draw;
+mod equiv;
+mod export;
+mod format;
+mod include;
 mod int_tree;
+mod library;
 mod lines;
+mod lint;
+mod memory;
+mod minimize;
+mod noise;
+mod observable;
+mod optimize;
+mod params;
 mod process;
 mod program;
+mod resources;
+mod sample;
+mod sim;
+mod state;
+mod sweep;
+mod testing;
+mod timings;
+mod transpile;
 mod utils;
+mod watch;
 
 fn main() -> program::ProgramResult<()> {
-    program::Program::new()?.run()?;
+    let cli = cli::CliArgs::new();
+    match &cli.command {
+        Some(cli::Command::Test { dir, junit }) => testing::run(&cli, dir, junit.as_deref())?,
+        Some(cli::Command::Check { file }) => lint::run(&cli, file)?,
+        Some(cli::Command::Fmt { file, check }) => format::run(file, *check)?,
+        None => program::Program::new(cli)?.run()?,
+    }
     Ok(())
 }
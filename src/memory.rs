@@ -0,0 +1,195 @@
+use std::fmt;
+
+/// Limit of the memory, which simulation is allowed to allocate, if available memory is unknown.
+pub const DEFAULT_MAX_MEMORY: u64 = 8 << 30;
+/// Estimate above this share of the limit requires confirmation in interactive mode.
+const NEAR_LIMIT: f64 = 0.5;
+/// Bytes per amplitude: `Complex<f64>`.
+const AMPLITUDE: u64 = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    TooManyQubits {
+        q_num: usize,
+        max_qubits: usize,
+    },
+    TooMuchMemory {
+        q_num: usize,
+        needed: u64,
+        max_memory: u64,
+    },
+    Declined(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::TooManyQubits { q_num, max_qubits } => write!(
+                f,
+                "Circuit has {q_num} qubits, but at most {max_qubits} are allowed (see --max-qubits)"
+            ),
+            Error::TooMuchMemory {
+                q_num,
+                needed,
+                max_memory,
+            } => write!(
+                f,
+                "Simulation of {q_num} qubits needs {} of memory, but the limit is {} (see --max-memory)",
+                format_size(needed),
+                format_size(max_memory)
+            ),
+            Error::Declined(q_num) => write!(f, "Simulation of {q_num} qubits is cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Memory for a single copy of the wavefunction of `q_num` qubits.
+fn state_size(q_num: usize) -> u64 {
+    1_u64
+        .checked_shl(q_num as u32)
+        .and_then(|size| size.max(8).checked_mul(AMPLITUDE))
+        .unwrap_or(u64::MAX)
+}
+
+/// Estimates memory for simulation of `q_num` qubits:
+/// wavefunction and the buffer, which operators are applied into.
+pub fn estimate(q_num: usize) -> u64 {
+    estimate_with(q_num, 0)
+}
+
+/// Estimates memory for simulation, which keeps `copies` more copies of the wavefunction, e.g. snapshots.
+pub fn estimate_with(q_num: usize, copies: u64) -> u64 {
+    state_size(q_num).saturating_mul(2_u64.saturating_add(copies))
+}
+
+/// Memory, which is available to the process according to the system, if it's known.
+pub fn available() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    kib.checked_mul(1 << 10)
+}
+
+/// Parses memory size as number of bytes with optional `K`, `M`, `G` or `T` binary suffix, e.g. `512M`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let upper = s.to_ascii_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, shift) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 10),
+        Some('M') => (&digits[..digits.len() - 1], 20),
+        Some('G') => (&digits[..digits.len() - 1], 30),
+        Some('T') => (&digits[..digits.len() - 1], 40),
+        _ => (digits, 0),
+    };
+    let value: f64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid memory size {s:?}, expected e.g. `512M` or `16G`"))?;
+    Ok((value * (1_u64 << shift) as f64) as u64)
+}
+
+pub fn format_size(size: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{size} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    NearLimit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_memory: u64,
+    pub max_qubits: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_memory: DEFAULT_MAX_MEMORY,
+            max_qubits: None,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks simulation of `q_num` qubits, which keeps `copies` more copies of the wavefunction.
+    pub fn check(&self, q_num: usize, copies: u64) -> Result<Verdict, Error> {
+        if let Some(max_qubits) = self.max_qubits {
+            if q_num > max_qubits {
+                return Err(Error::TooManyQubits { q_num, max_qubits });
+            }
+        }
+        let estimate = estimate_with(q_num, copies);
+        if estimate > self.max_memory {
+            Err(Error::TooMuchMemory {
+                q_num,
+                needed: estimate,
+                max_memory: self.max_memory,
+            })
+        } else if estimate as f64 > self.max_memory as f64 * NEAR_LIMIT {
+            Ok(Verdict::NearLimit)
+        } else {
+            Ok(Verdict::Allowed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("16G"), Ok(16 << 30));
+        assert_eq!(parse_size("1.5kib"), Ok(1536));
+        assert!(parse_size("lots").is_err());
+
+        assert_eq!(format_size(100), "100 B");
+        assert_eq!(format_size(estimate(30)), "32.0 GiB");
+        assert_eq!(estimate(64), u64::MAX);
+    }
+
+    #[test]
+    fn limits() {
+        let limits = Limits::default();
+        assert_eq!(limits.check(20, 0), Ok(Verdict::Allowed));
+        assert_eq!(limits.check(28, 0), Ok(Verdict::NearLimit));
+        assert!(matches!(
+            limits.check(34, 0),
+            Err(Error::TooMuchMemory { q_num: 34, .. })
+        ));
+        // a snapshot of 28 qubits exceeds the default limit
+        assert!(matches!(
+            limits.check(28, 1),
+            Err(Error::TooMuchMemory { needed, .. }) if needed == 12 << 30
+        ));
+
+        let limits = Limits {
+            max_qubits: Some(10),
+            ..limits
+        };
+        assert!(matches!(
+            limits.check(11, 0),
+            Err(Error::TooManyQubits { .. })
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+use std::{fmt, str::FromStr};
+
+use rand::{prelude::*, rngs::StdRng};
+
+use crate::observable::Observable;
+
+/// Maximal number of Nelder-Mead iterations.
+const NM_MAX_ITERS: usize = 500;
+/// Initial size of Nelder-Mead simplex along every parameter.
+const NM_STEP: f64 = 0.5;
+/// Nelder-Mead stops, when both values and vertices of simplex are closer than this.
+const NM_TOL: f64 = 1e-8;
+
+/// Number of SPSA iterations.
+const SPSA_ITERS: usize = 200;
+/// Gain sequences of SPSA: `a / (k + 1 + A)^0.602` for the step and `c / (k + 1)^0.101` for the perturbation.
+const SPSA_A: f64 = 0.6;
+const SPSA_STABILITY: f64 = 10.0;
+const SPSA_C: f64 = 0.1;
+
+/// Number of rows of convergence trace, which are shown.
+const TRACE_ROWS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Method {
+    #[default]
+    NelderMead,
+    Spsa,
+}
+
+impl FromStr for Method {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s.to_lowercase().as_str() {
+            "nelder-mead" | "nm" => Ok(Method::NelderMead),
+            "spsa" => Ok(Method::Spsa),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::NelderMead => write!(f, "nelder-mead"),
+            Method::Spsa => write!(f, "spsa"),
+        }
+    }
+}
+
+/// Minimisation of the observable expectation value over circuit parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimize {
+    pub obs: Observable,
+    pub vars: Vec<String>,
+    /// Initial values of parameters, session variables (or zeros) are used if omitted.
+    pub from: Option<Vec<f64>>,
+    pub method: Method,
+}
+
+/// Result of the minimisation with the best point of every iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    vars: Vec<String>,
+    obs: String,
+    method: Method,
+    pub params: Vec<f64>,
+    pub value: f64,
+    evaluations: usize,
+    trace: Vec<(Vec<f64>, f64)>,
+}
+
+impl Minimize {
+    /// Minimises the value, returned by `simulate` for parameter values, starting from `start`.
+    /// SPSA perturbations are sampled from `seed`.
+    pub fn run<E>(
+        &self,
+        start: Vec<f64>,
+        seed: u64,
+        mut simulate: impl FnMut(&[f64]) -> Result<f64, E>,
+    ) -> Result<Outcome, E> {
+        let mut evaluations = 0;
+        let mut eval = |params: &[f64]| {
+            evaluations += 1;
+            simulate(params)
+        };
+        let trace = match self.method {
+            Method::NelderMead => nelder_mead(start, &mut eval)?,
+            Method::Spsa => spsa(start, seed, &mut eval)?,
+        };
+        let (params, value) = trace
+            .iter()
+            .min_by(|lhs, rhs| lhs.1.total_cmp(&rhs.1))
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(Outcome {
+            vars: self.vars.clone(),
+            obs: self.obs.to_string(),
+            method: self.method,
+            params,
+            value,
+            evaluations,
+            trace,
+        })
+    }
+}
+
+fn nelder_mead<E>(
+    start: Vec<f64>,
+    f: &mut impl FnMut(&[f64]) -> Result<f64, E>,
+) -> Result<Vec<(Vec<f64>, f64)>, E> {
+    let n = start.len();
+    let mut simplex = Vec::with_capacity(n + 1);
+    for idx in 0..=n {
+        let mut point = start.clone();
+        if idx < n {
+            point[idx] += NM_STEP;
+        }
+        let value = f(&point)?;
+        simplex.push((point, value));
+    }
+
+    let towards = |from: &[f64], to: &[f64], coef: f64| -> Vec<f64> {
+        from.iter()
+            .zip(to)
+            .map(|(a, b)| a + coef * (b - a))
+            .collect()
+    };
+    let mut trace = vec![];
+    for _ in 0..NM_MAX_ITERS {
+        simplex.sort_by(|lhs, rhs| lhs.1.total_cmp(&rhs.1));
+        trace.push(simplex[0].clone());
+
+        let (best, worst) = (&simplex[0], &simplex[n]);
+        let size = simplex
+            .iter()
+            .flat_map(|(point, _)| point.iter().zip(&best.0).map(|(a, b)| (a - b).abs()))
+            .fold(0.0, f64::max);
+        if worst.1 - best.1 < NM_TOL && size < NM_TOL.sqrt() {
+            break;
+        }
+
+        let centroid: Vec<f64> = (0..n)
+            .map(|idx| {
+                simplex[..n]
+                    .iter()
+                    .map(|(point, _)| point[idx])
+                    .sum::<f64>()
+                    / n as f64
+            })
+            .collect();
+        let reflected = towards(&centroid, &worst.0, -1.0);
+        let f_reflected = f(&reflected)?;
+
+        if f_reflected < best.1 {
+            let expanded = towards(&centroid, &worst.0, -2.0);
+            let f_expanded = f(&expanded)?;
+            simplex[n] = match f_expanded < f_reflected {
+                true => (expanded, f_expanded),
+                false => (reflected, f_reflected),
+            };
+        } else if f_reflected < simplex[n - 1].1 {
+            simplex[n] = (reflected, f_reflected);
+        } else {
+            let contracted = match f_reflected < worst.1 {
+                true => towards(&centroid, &reflected, 0.5),
+                false => towards(&centroid, &worst.0, 0.5),
+            };
+            let f_contracted = f(&contracted)?;
+            if f_contracted < f_reflected.min(worst.1) {
+                simplex[n] = (contracted, f_contracted);
+            } else {
+                let best = simplex[0].0.clone();
+                for vertex in &mut simplex[1..] {
+                    vertex.0 = towards(&best, &vertex.0, 0.5);
+                    vertex.1 = f(&vertex.0)?;
+                }
+            }
+        }
+    }
+    Ok(trace)
+}
+
+fn spsa<E>(
+    start: Vec<f64>,
+    seed: u64,
+    f: &mut impl FnMut(&[f64]) -> Result<f64, E>,
+) -> Result<Vec<(Vec<f64>, f64)>, E> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut point = start;
+    let mut trace = vec![];
+    for k in 0..SPSA_ITERS {
+        let a = SPSA_A / (k as f64 + 1.0 + SPSA_STABILITY).powf(0.602);
+        let c = SPSA_C / (k as f64 + 1.0).powf(0.101);
+        let delta: Vec<f64> = point
+            .iter()
+            .map(|_| if rng.gen::<bool>() { 1.0 } else { -1.0 })
+            .collect();
+
+        let shifted = |sign: f64| -> Vec<f64> {
+            point
+                .iter()
+                .zip(&delta)
+                .map(|(x, d)| x + sign * c * d)
+                .collect()
+        };
+        let diff = f(&shifted(1.0))? - f(&shifted(-1.0))?;
+        for (x, d) in point.iter_mut().zip(&delta) {
+            *x -= a * diff / (2.0 * c) * d;
+        }
+        trace.push((point.clone(), f(&point)?));
+    }
+    Ok(trace)
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.obs.len().max(9);
+        write!(f, "{:>5}  {:>width$}", "iter", self.obs)?;
+        for var in &self.vars {
+            write!(f, "  {var:>8}")?;
+        }
+        writeln!(f)?;
+
+        let step = self.trace.len().div_ceil(TRACE_ROWS);
+        for (idx, (params, value)) in self.trace.iter().enumerate() {
+            if idx % step.max(1) != 0 && idx + 1 != self.trace.len() {
+                continue;
+            }
+            write!(f, "{:>5}  {value:>width$.6}", idx + 1)?;
+            for param in params {
+                write!(f, "  {param:>8.4}")?;
+            }
+            writeln!(f)?;
+        }
+
+        let params: Vec<_> = self
+            .vars
+            .iter()
+            .zip(&self.params)
+            .map(|(var, param)| format!("{var} = {param:.6}"))
+            .collect();
+        write!(
+            f,
+            "Minimum {:.6} by {} after {} iterations ({} evaluations): {}",
+            self.value,
+            self.method,
+            self.trace.len(),
+            self.evaluations,
+            params.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimize(method: Method) -> Outcome {
+        let minimize = Minimize {
+            obs: "Z0".parse().unwrap(),
+            vars: vec!["x".to_string(), "y".to_string()],
+            from: None,
+            method,
+        };
+        // minimum -1 is at x = pi, y = 1
+        minimize
+            .run(vec![0.3, 0.2], 1, |params| -> Result<f64, ()> {
+                Ok(params[0].cos() + (params[1] - 1.0).powi(2))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn nelder_mead() {
+        let outcome = minimize(Method::NelderMead);
+        assert!((outcome.value + 1.0).abs() < 1e-6);
+        assert!((outcome.params[0].abs() - std::f64::consts::PI).abs() < 1e-3);
+        assert!((outcome.params[1] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn spsa() {
+        let outcome = minimize(Method::Spsa);
+        assert!((outcome.value + 1.0).abs() < 1e-2);
+        assert_eq!(outcome.evaluations, 3 * SPSA_ITERS);
+    }
+}
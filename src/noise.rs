@@ -0,0 +1,217 @@
+use std::fmt;
+
+use num_complex::Complex64 as C;
+use rand::Rng;
+
+use crate::circuit::{self, Circuit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Random Pauli `X`, `Y` or `Z` with the total probability `p`.
+    Depolarizing,
+    /// Pauli `X` with probability `p`.
+    BitFlip,
+    /// Measured bit is recorded flipped with probability `p`.
+    Readout,
+    /// Decay of `|1>` to `|0>` with the rate `p`, sampled from Kraus operators.
+    AmplitudeDamping,
+}
+
+impl Channel {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "depolarizing" => Some(Channel::Depolarizing),
+            "bitflip" => Some(Channel::BitFlip),
+            "readout" => Some(Channel::Readout),
+            "amplitude-damping" => Some(Channel::AmplitudeDamping),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Depolarizing => write!(f, "depolarizing"),
+            Channel::BitFlip => write!(f, "bitflip"),
+            Channel::Readout => write!(f, "readout"),
+            Channel::AmplitudeDamping => write!(f, "amplitude-damping"),
+        }
+    }
+}
+
+/// Noise channel, applied to qubits of `target` (e.g. `q[2]` or `q`) or to all qubits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub channel: Channel,
+    pub target: Option<String>,
+    pub prob: f64,
+}
+
+impl Rule {
+    /// Parses `CHANNEL [TARGET] P` with probability `P` in `[0, 1]`.
+    pub fn parse(channel: &str, target: Option<&str>, prob: &str) -> Option<Self> {
+        Some(Self {
+            channel: Channel::parse(channel)?,
+            target: target.map(str::to_string),
+            prob: prob
+                .parse()
+                .ok()
+                .filter(|prob| (0.0..=1.0).contains(prob))?,
+        })
+    }
+
+    /// Resolves the target against registers of the circuit.
+    pub fn resolve(&self, circuit: &Circuit) -> circuit::Result<Noise> {
+        let q_mask = match &self.target {
+            Some(target) => circuit.q_mask_of(target)?,
+            None => usize::MAX,
+        };
+        Ok(Noise {
+            channel: self.channel,
+            prob: self.prob,
+            q_mask,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Some(target) => write!(f, "{} {} {}", self.channel, target, self.prob),
+            None => write!(f, "{} {}", self.channel, self.prob),
+        }
+    }
+}
+
+/// Noise rule with qubits of the target as a mask.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Noise {
+    pub channel: Channel,
+    pub prob: f64,
+    pub q_mask: usize,
+}
+
+impl Noise {
+    /// Injects errors on `act_on` qubits after a gate, measurement or reset.
+    /// Readout errors are applied by the simulator to recorded bits instead.
+    pub fn after_op(&self, act_on: usize, psi: &mut [C], rng: &mut impl Rng) {
+        for bit in circuit::bits(act_on & self.q_mask) {
+            let mask = 1 << bit;
+            match self.channel {
+                Channel::Depolarizing if rng.gen_bool(self.prob) => match rng.gen_range(0..3) {
+                    0 => pauli_x(mask, psi),
+                    1 => pauli_y(mask, psi),
+                    _ => pauli_z(mask, psi),
+                },
+                Channel::BitFlip if rng.gen_bool(self.prob) => pauli_x(mask, psi),
+                Channel::AmplitudeDamping => amplitude_damping(mask, self.prob, psi, rng),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn pauli_x(mask: usize, psi: &mut [C]) {
+    for idx in (0..psi.len()).filter(|idx| idx & mask == 0) {
+        psi.swap(idx, idx | mask);
+    }
+}
+
+fn pauli_y(mask: usize, psi: &mut [C]) {
+    for idx in (0..psi.len()).filter(|idx| idx & mask == 0) {
+        let (amp0, amp1) = (psi[idx], psi[idx | mask]);
+        psi[idx] = -C::i() * amp1;
+        psi[idx | mask] = C::i() * amp0;
+    }
+}
+
+fn pauli_z(mask: usize, psi: &mut [C]) {
+    for (_, amp) in psi
+        .iter_mut()
+        .enumerate()
+        .filter(|(idx, _)| idx & mask != 0)
+    {
+        *amp = -*amp;
+    }
+}
+
+/// Samples one of Kraus operators `K0 = |0><0| + sqrt(1 - gamma) |1><1|` and `K1 = sqrt(gamma) |0><1|`
+/// and applies it with renormalisation.
+fn amplitude_damping(mask: usize, gamma: f64, psi: &mut [C], rng: &mut impl Rng) {
+    let norm: f64 = psi.iter().map(C::norm_sqr).sum();
+    let excited: f64 = psi
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| idx & mask != 0)
+        .map(|(_, amp)| amp.norm_sqr())
+        .sum();
+    if norm == 0.0 || excited == 0.0 {
+        return;
+    }
+
+    let decay = gamma * excited / norm;
+    if rng.gen_bool(decay.min(1.0)) {
+        let scale = (norm / excited).sqrt();
+        for idx in (0..psi.len()).filter(|idx| idx & mask == 0) {
+            psi[idx] = psi[idx | mask] * scale;
+            psi[idx | mask] = C::default();
+        }
+    } else {
+        let scale = (norm / (norm - decay * norm)).sqrt();
+        for (idx, amp) in psi.iter_mut().enumerate() {
+            match idx & mask {
+                0 => *amp *= scale,
+                _ => *amp *= scale * (1.0 - gamma).sqrt(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let rule = Rule::parse("bitflip", Some("q[2]"), "0.05").unwrap();
+        assert_eq!(rule.to_string(), "bitflip q[2] 0.05");
+        assert_eq!(
+            Rule::parse("depolarizing", None, "0.01").map(|rule| rule.channel),
+            Some(Channel::Depolarizing)
+        );
+        assert_eq!(Rule::parse("readout", None, "1.5"), None);
+        assert_eq!(Rule::parse("dephasing", None, "0.1"), None);
+    }
+
+    #[test]
+    fn channels() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sqrt = std::f64::consts::FRAC_1_SQRT_2;
+
+        let mut psi = vec![C::new(1.0, 0.0), C::default(), C::default(), C::default()];
+        let flip = Noise {
+            channel: Channel::BitFlip,
+            prob: 1.0,
+            q_mask: 0b10,
+        };
+        flip.after_op(0b11, &mut psi, &mut rng);
+        assert_eq!(psi[0b10], C::new(1.0, 0.0));
+
+        let mut psi = vec![C::new(sqrt, 0.0), C::new(sqrt, 0.0)];
+        let damping = Noise {
+            channel: Channel::AmplitudeDamping,
+            prob: 1.0,
+            q_mask: usize::MAX,
+        };
+        damping.after_op(0b1, &mut psi, &mut rng);
+        assert!((psi[0].norm_sqr() - 1.0).abs() < 1e-12);
+        assert_eq!(psi[1], C::default());
+
+        let mut psi = vec![C::new(sqrt, 0.0), C::new(sqrt, 0.0)];
+        pauli_y(0b1, &mut psi);
+        assert_eq!(psi, [C::new(0.0, -sqrt), C::new(0.0, sqrt)]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{fmt, str::FromStr};
+
+use num_complex::Complex64 as C;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidToken(String),
+    EmptyTerm,
+    RepeatedQubit(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidToken(token) => write!(
+                f,
+                "Invalid token {token:?} of observable: expected coefficient or Pauli operator like `Z0`"
+            ),
+            Error::EmptyTerm => write!(f, "Observable has an empty term"),
+            Error::RepeatedQubit(token) => write!(
+                f,
+                "Pauli operator {token:?} acts on the qubit, which already has a factor in the term"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Product of Pauli operators, stored as masks of qubits with `X` or `Y` and `Z` or `Y` factors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pauli {
+    pub x: usize,
+    pub z: usize,
+}
+
+impl Pauli {
+    pub fn q_mask(&self) -> usize {
+        self.x | self.z
+    }
+
+    /// Expectation value `<psi|P|psi>` in the normalised state.
+    pub fn expectation(&self, psi: &[C]) -> f64 {
+        // `Y = iXZ`, so every `Y` adds `i` to the phase
+        let phase = C::i().powu((self.x & self.z).count_ones());
+        let value: C = psi
+            .iter()
+            .enumerate()
+            .map(|(idx, amp)| {
+                let sign = match (idx & self.z).count_ones() & 1 {
+                    0 => 1.0,
+                    _ => -1.0,
+                };
+                psi[idx ^ self.x].conj() * amp * sign
+            })
+            .sum();
+        (phase * value).re
+    }
+}
+
+impl fmt::Display for Pauli {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.q_mask() == 0 {
+            return write!(f, "I");
+        }
+        let factors: Vec<_> = crate::circuit::bits(self.q_mask())
+            .map(|bit| {
+                let name = match (self.x >> bit & 1, self.z >> bit & 1) {
+                    (1, 0) => 'X',
+                    (1, 1) => 'Y',
+                    _ => 'Z',
+                };
+                format!("{name}{bit}")
+            })
+            .collect();
+        write!(f, "{}", factors.join(" "))
+    }
+}
+
+/// Weighted sum of Pauli products, e.g. `Z0 Z1 - 0.5*X0 + Y1`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Observable(pub Vec<(f64, Pauli)>);
+
+impl Observable {
+    pub fn q_mask(&self) -> usize {
+        self.0
+            .iter()
+            .fold(0, |acc, (_, pauli)| acc | pauli.q_mask())
+    }
+
+    pub fn expectation(&self, psi: &[C]) -> f64 {
+        self.0
+            .iter()
+            .map(|(coef, pauli)| coef * pauli.expectation(psi))
+            .sum()
+    }
+}
+
+impl FromStr for Observable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut terms = vec![];
+        // coefficient and Pauli product of the current term, and if it has any factor yet
+        let mut term = (1.0, Pauli::default(), false);
+
+        let tokens = s
+            .split(|c: char| c.is_ascii_whitespace() || c == '*')
+            .filter(|token| !token.is_empty());
+        for token in tokens {
+            let (sign, token) = match token.as_bytes()[0] {
+                b'+' => (Some(1.0), &token[1..]),
+                b'-' => (Some(-1.0), &token[1..]),
+                _ => (None, token),
+            };
+            match sign {
+                Some(sign) if term.2 => {
+                    terms.push((term.0, term.1));
+                    term = (sign, Pauli::default(), false);
+                }
+                Some(sign) => term.0 *= sign,
+                None => {}
+            }
+            if token.is_empty() {
+                continue;
+            }
+
+            if let Ok(coef) = token.parse::<f64>() {
+                term.0 *= coef;
+                term.2 = true;
+                continue;
+            }
+            let invalid = || Error::InvalidToken(token.to_string());
+            let first = token.chars().next().map_or(0, char::len_utf8);
+            let (name, idx) = token.split_at(first);
+            let bit = 1_usize
+                .checked_shl(idx.parse::<u32>().map_err(|_| invalid())?)
+                .ok_or_else(invalid)?;
+            let (x, z) = match name {
+                "X" | "x" => (bit, 0),
+                "Z" | "z" => (0, bit),
+                "Y" | "y" => (bit, bit),
+                "I" | "i" => (0, 0),
+                _ => return Err(invalid()),
+            };
+            // product of factors on the same qubit is not a Pauli operator up to a real sign
+            if term.1.q_mask() & (x | z) != 0 {
+                return Err(Error::RepeatedQubit(token.to_string()));
+            }
+            term.1.x |= x;
+            term.1.z |= z;
+            term.2 = true;
+        }
+
+        if !term.2 {
+            return Err(Error::EmptyTerm);
+        }
+        terms.push((term.0, term.1));
+        Ok(Self(terms))
+    }
+}
+
+impl fmt::Display for Observable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (coef, pauli)) in self.0.iter().enumerate() {
+            let sign = match (idx, coef.is_sign_negative()) {
+                (0, false) => "",
+                (0, true) => "-",
+                (_, false) => " + ",
+                (_, true) => " - ",
+            };
+            if coef.abs() == 1.0 {
+                write!(f, "{sign}{pauli}")?;
+            } else {
+                write!(f, "{sign}{}*{pauli}", coef.abs())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let obs: Observable = "Z0 Z1 - 0.5*X0 + -2 Y2".parse().unwrap();
+        assert_eq!(
+            obs.0,
+            vec![
+                (1.0, Pauli { x: 0, z: 0b11 }),
+                (-0.5, Pauli { x: 0b1, z: 0 }),
+                (-2.0, Pauli { x: 0b100, z: 0b100 }),
+            ]
+        );
+        assert_eq!(obs.to_string(), "Z0 Z1 - 0.5*X0 - 2*Y2");
+        assert_eq!(
+            "Z0 + W1".parse::<Observable>(),
+            Err(Error::InvalidToken("W1".to_string()))
+        );
+        assert_eq!(
+            "é0".parse::<Observable>(),
+            Err(Error::InvalidToken("é0".to_string()))
+        );
+        assert_eq!("Z0 +".parse::<Observable>(), Err(Error::EmptyTerm));
+        assert_eq!(
+            "X0 Z0".parse::<Observable>(),
+            Err(Error::RepeatedQubit("Z0".to_string()))
+        );
+        assert_eq!(
+            "Z1 X0 + Z1 Z1".parse::<Observable>(),
+            Err(Error::RepeatedQubit("Z1".to_string()))
+        );
+    }
+
+    #[test]
+    fn expectation() {
+        let sqrt = std::f64::consts::FRAC_1_SQRT_2;
+        // (|00> + i|11>) / sqrt(2)
+        let psi = [
+            C::new(sqrt, 0.0),
+            C::default(),
+            C::default(),
+            C::new(0.0, sqrt),
+        ];
+        let value = |obs: &str| obs.parse::<Observable>().unwrap().expectation(&psi);
+
+        assert!((value("Z0 Z1") - 1.0).abs() < 1e-12);
+        assert!(value("Z0").abs() < 1e-12);
+        assert!(value("X0 X1").abs() < 1e-12);
+        assert!((value("X0 Y1") - 1.0).abs() < 1e-12);
+        assert!((value("2*Z0 Z1 - X0 Y1") - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,230 @@
+use std::{f64::consts::PI, fmt};
+
+use crate::{
+    circuit::{self, Circuit, Gate, Op},
+    resources::Resources,
+};
+
+/// Angles closer than this to the identity rotation are dropped.
+const EPS: f64 = 1e-9;
+
+/// Gates, which are split into single-qubit applications, when applied to the whole register.
+const SPLIT_GATES: &[&str] = &["x", "y", "z", "s", "sdg", "t", "tdg", "h"];
+
+/// Pairs of gates, which cancel each other, when applied to the same qubits.
+const INVERSE_GATES: &[(&str, &str)] = &[
+    ("x", "x"),
+    ("y", "y"),
+    ("z", "z"),
+    ("h", "h"),
+    ("swap", "swap"),
+    ("s", "sdg"),
+    ("sdg", "s"),
+    ("t", "tdg"),
+    ("tdg", "t"),
+];
+
+/// Rotations, which are merged by summing their angles.
+const ROTATION_GATES: &[&str] = &["rx", "ry", "rz", "rxx", "ryy", "rzz", "u1"];
+
+/// Changes, made by the optimisation passes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub cancelled: usize,
+    pub merged: usize,
+    pub dropped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub before: Resources,
+    pub after: Resources,
+    pub stats: Stats,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = |before: usize, after: usize| {
+            format!("{before} -> {after} ({:+})", after as i64 - before as i64)
+        };
+        writeln!(
+            f,
+            "Gates: {}",
+            change(self.before.gate_count(), self.after.gate_count())
+        )?;
+        writeln!(f, "Depth: {}", change(self.before.depth, self.after.depth))?;
+        write!(
+            f,
+            "Cancelled pairs: {}, merged rotations: {}, dropped rotations: {}",
+            self.stats.cancelled, self.stats.merged, self.stats.dropped
+        )
+    }
+}
+
+/// Simplifies the circuit with peephole passes over its gates:
+/// cancels adjacent inverse gates, merges consecutive rotations around the same axis
+/// and drops rotations by multiples of `2pi`.
+///
+/// Macros are expanded beforehand. Gates are adjacent, if no instruction in between touches their qubits.
+pub fn optimize(circuit: &Circuit) -> (Circuit, Report) {
+    let mut optimized = circuit.flatten();
+    let mut stats = Stats::default();
+
+    let mut ops: Vec<Op> = Vec::with_capacity(optimized.ops.len());
+    for op in split(std::mem::take(&mut optimized.ops)) {
+        let gate = match op {
+            Op::Gate(gate) => gate,
+            op => {
+                ops.push(op);
+                continue;
+            }
+        };
+        if is_identity(&gate) {
+            stats.dropped += 1;
+            continue;
+        }
+
+        let prev = ops.iter().rposition(|op| op.act_on() & gate.act_on() != 0);
+        if let Some(idx) = prev {
+            if let Op::Gate(prev) = &mut ops[idx] {
+                if is_inverse(prev, &gate) {
+                    ops.remove(idx);
+                    stats.cancelled += 1;
+                    continue;
+                }
+                if is_same_rotation(prev, &gate) {
+                    prev.args[0] += gate.args[0];
+                    stats.merged += 1;
+                    if is_identity(prev) {
+                        ops.remove(idx);
+                        stats.dropped += 1;
+                    }
+                    continue;
+                }
+            }
+        }
+        ops.push(Op::Gate(gate));
+    }
+    optimized.ops = ops;
+
+    let report = Report {
+        before: Resources::of(circuit),
+        after: Resources::of(&optimized),
+        stats,
+    };
+    (optimized, report)
+}
+
+/// Splits uncontrolled single-qubit gates, applied to several qubits, into separate applications.
+fn split(ops: Vec<Op>) -> impl Iterator<Item = Op> {
+    ops.into_iter().flat_map(|op| match op {
+        Op::Gate(gate)
+            if gate.regs.len() == 1
+                && gate.act_on().count_ones() > 1
+                && SPLIT_GATES.contains(&gate.name.to_lowercase().as_str()) =>
+        {
+            circuit::bits(gate.act_on())
+                .map(|bit| {
+                    Op::Gate(Gate {
+                        regs: vec![1 << bit],
+                        ..gate.clone()
+                    })
+                })
+                .collect()
+        }
+        op => vec![op],
+    })
+}
+
+fn is_inverse(lhs: &Gate, rhs: &Gate) -> bool {
+    let (lhs_ctrl, lhs_name) = lhs.controls();
+    let (rhs_ctrl, rhs_name) = rhs.controls();
+    let (lhs_name, rhs_name) = (lhs_name.to_lowercase(), rhs_name.to_lowercase());
+    lhs_ctrl == rhs_ctrl
+        && lhs.regs == rhs.regs
+        && lhs.args.is_empty()
+        && rhs.args.is_empty()
+        && INVERSE_GATES.contains(&(lhs_name.as_str(), rhs_name.as_str()))
+}
+
+fn is_same_rotation(lhs: &Gate, rhs: &Gate) -> bool {
+    lhs.name.eq_ignore_ascii_case(&rhs.name)
+        && lhs.regs == rhs.regs
+        && lhs.args.len() == 1
+        && rhs.args.len() == 1
+        && ROTATION_GATES.contains(&lhs.controls().1.to_lowercase().as_str())
+}
+
+/// Checks the gate is a rotation, which is identity up to global phase.
+///
+/// Rotations by `2pi` (except of `u1`) change the sign of the state,
+/// so controlled ones are the identity only for multiples of `4pi`.
+fn is_identity(gate: &Gate) -> bool {
+    let (ctrl, name) = gate.controls();
+    let name = name.to_lowercase();
+    if gate.args.len() != 1 || !ROTATION_GATES.contains(&name.as_str()) {
+        return false;
+    }
+    let period = if ctrl == 0 || name == "u1" {
+        2.0 * PI
+    } else {
+        4.0 * PI
+    };
+    let angle = gate.args[0].rem_euclid(period);
+    angle < EPS || period - angle < EPS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+    use crate::equiv;
+
+    #[test]
+    fn peephole() {
+        let circuit = circuit(
+            "qreg q[3]; creg c[1];
+            gate foo a, b { cx a, b; t b; }
+            h q; h q[0]; x q[2]; foo q[1], q[2]; tdg q[2]; cx q[1], q[2]; x q[2];
+            rz(pi/3) q[1]; rz(2*pi/3) q[1]; rz(pi) q[1]; crz(2*pi) q[0], q[1];
+            measure q[1] -> c[0]; ry(4*pi) q[2];",
+        );
+        let (optimized, report) = optimize(&circuit);
+
+        assert_eq!(
+            optimized.to_qasm().unwrap(),
+            "OPENQASM 2.0;
+qreg q[3];
+creg c[1];
+h q[1];
+h q[2];
+crz(2*pi) q[0], q[1];
+measure q[1] -> c;
+"
+        );
+        assert_eq!(
+            report.stats,
+            Stats {
+                cancelled: 4,
+                merged: 2,
+                dropped: 2,
+            }
+        );
+        assert_eq!(report.before.gate_count(), 15);
+        assert_eq!(report.after.gate_count(), 3);
+    }
+
+    #[test]
+    fn equivalent() {
+        let circuit = circuit(
+            "qreg q[2]; h q; rx(1) q[0]; cz q[0], q[1]; rx(-1) q[0]; cz q[0], q[1]; s q[1]; sdg q[1];",
+        );
+        let (optimized, report) = optimize(&circuit);
+
+        assert_eq!(report.stats.cancelled, 1);
+        assert_eq!(
+            equiv::check(&circuit, &optimized).unwrap().verdict,
+            equiv::Verdict::Equivalent
+        );
+    }
+}
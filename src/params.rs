@@ -0,0 +1,121 @@
+use meval::tokenizer::Token;
+use qasm::AstNode;
+use qvnt::qasm::Ast;
+
+use crate::circuit;
+
+/// Checks `name` is a valid name of parameter or session variable.
+pub fn is_var(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Identifies AST of a program line by its source, which is leaked and never moved.
+pub fn key(ast: &Ast<'_>) -> usize {
+    ast.source().as_ptr() as usize
+}
+
+/// Arguments of gate applications, which are slices of the AST source.
+fn gate_args<'t>(ast: &Ast<'t>) -> Vec<&'t str> {
+    fn collect<'t>(node: AstNode<'t>, args: &mut Vec<&'t str>) {
+        match node {
+            AstNode::ApplyGate(_, _, node_args) => args.extend(node_args),
+            AstNode::If(_, _, node) => collect(*node, args),
+            _ => {}
+        }
+    }
+
+    let mut args = vec![];
+    for node in ast.clone() {
+        collect(node, &mut args);
+    }
+    args
+}
+
+/// Checks, whether any gate argument of `ast` refers to the variable `var`.
+pub fn references(ast: &Ast<'_>, var: &str) -> bool {
+    gate_args(ast)
+        .into_iter()
+        .any(|arg| match arg.parse::<meval::Expr>() {
+            Ok(expr) => expr
+                .iter()
+                .any(|token| matches!(token, Token::Var(name) if name == var)),
+            Err(_) => false,
+        })
+}
+
+/// Evaluates gate argument with parameters from `vars`, failing on unknown ones.
+/// Returns `None`, if the argument has no parameters or cannot be evaluated anyway.
+fn eval_params(arg: &str, vars: &[(String, f64)]) -> circuit::Result<Option<f64>> {
+    if circuit::eval(arg, None).is_ok() {
+        return Ok(None);
+    }
+    match circuit::eval(arg, vars.iter().map(|(var, val)| (var.as_str(), *val))) {
+        Ok(value) => Ok(Some(value)),
+        Err(err @ circuit::Error::UnevaluatedArgument(_, meval::Error::UnknownVariable(_))) => {
+            Err(err)
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Rewrites the source of `ast` with parametrised gate arguments (e.g. `theta` in `rx(theta) q[0];`)
+/// replaced by numbers, so `qvnt` is able to build the program. Parameters are not known to `qvnt`,
+/// that's why the line is simulated from the original AST with parameters bound on lowering.
+///
+/// Returns `None`, if there are no parameters in the line, or an error, if some of them aren't in `vars`.
+pub fn bind(ast: &Ast<'_>, vars: &[(String, f64)]) -> circuit::Result<Option<String>> {
+    let source = ast.source();
+    let mut bound = String::new();
+    let mut last = 0;
+    for arg in gate_args(ast) {
+        let start = match (arg.as_ptr() as usize).checked_sub(source.as_ptr() as usize) {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        let end = start + arg.len();
+        if start < last || end > source.len() {
+            return Ok(None);
+        }
+        if let Some(value) = eval_params(arg, vars)? {
+            bound += &source[last..start];
+            bound += &format!("({value:?})");
+            last = end;
+        }
+    }
+    Ok((last > 0).then(|| bound + &source[last..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_args() {
+        let source = "qreg q[2]; gate rot(t) a { rx(t) a; } rx(theta) q[0]; rot(pi/2) q[1]; \
+            u3(2*theta, phi, pi) q[1];";
+        let ast = Ast::from_source(source).unwrap();
+        let vars = [("theta".to_string(), 0.25), ("phi".to_string(), 1.5)];
+        assert_eq!(
+            bind(&ast, &vars),
+            Ok(Some(
+                "qreg q[2]; gate rot(t) a { rx(t) a; } rx((0.25)) q[0]; rot(pi/2) q[1]; \
+                u3((0.5), (1.5), pi) q[1];"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            bind(&Ast::from_source("qreg q[1]; rx(pi) q[0];").unwrap(), &[]),
+            Ok(None)
+        );
+        assert!(matches!(
+            bind(&ast, &vars[1..]),
+            Err(circuit::Error::UnevaluatedArgument(_, meval::Error::UnknownVariable(var))) if var == "theta"
+        ));
+
+        assert!(references(&ast, "theta"));
+        assert!(references(&ast, "phi"));
+        assert!(!references(&ast, "t"));
+        assert!(!references(&ast, "x"));
+    }
+}
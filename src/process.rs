@@ -1,11 +1,31 @@
-use std::{collections::HashMap, fmt, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use qvnt::qasm::{Ast, Int, Sym};
+use qvnt::qasm::{Ast, Int};
 
 use crate::{
+    assertion,
+    circuit::{self, Circuit},
+    debug::{self, Debugger},
+    draw, equiv, export, include,
     int_tree::Tree,
+    library,
     lines::{self, Command, Line},
+    lint,
+    memory::{self, Limits},
+    noise, optimize, params,
+    resources::Resources,
+    sim::Sim,
+    state, sweep,
+    timings::Timings,
+    transpile,
     utils::{drop_leakage, owned_errors, owned_errors::ToOwnedError},
+    watch::{self, Watch},
 };
 
 #[derive(Debug)]
@@ -14,6 +34,14 @@ pub enum Error {
     Lines(lines::Error),
     Int(owned_errors::int::OwnedError),
     Ast(owned_errors::ast::OwnedError),
+    Circuit(circuit::Error),
+    State(state::Error),
+    Memory(memory::Error),
+    Sweep(sweep::Error),
+    ThreadPool(rayon::ThreadPoolBuildError),
+    Transpile(transpile::Error),
+    Include(include::Error),
+    Assertion(assertion::Failure),
     Inner,
     #[allow(dead_code)]
     Unimplemented,
@@ -37,6 +65,48 @@ impl From<lines::Error> for Error {
     }
 }
 
+impl From<circuit::Error> for Error {
+    fn from(err: circuit::Error) -> Self {
+        Self::Circuit(err)
+    }
+}
+
+impl From<state::Error> for Error {
+    fn from(err: state::Error) -> Self {
+        Self::State(err)
+    }
+}
+
+impl From<memory::Error> for Error {
+    fn from(err: memory::Error) -> Self {
+        Self::Memory(err)
+    }
+}
+
+impl From<sweep::Error> for Error {
+    fn from(err: sweep::Error) -> Self {
+        Self::Sweep(err)
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for Error {
+    fn from(err: rayon::ThreadPoolBuildError) -> Self {
+        Self::ThreadPool(err)
+    }
+}
+
+impl From<transpile::Error> for Error {
+    fn from(err: transpile::Error) -> Self {
+        Self::Transpile(err)
+    }
+}
+
+impl From<include::Error> for Error {
+    fn from(err: include::Error) -> Self {
+        Self::Include(err)
+    }
+}
+
 impl<'t> From<qvnt::qasm::int::Error<'t>> for Error {
     fn from(err: qvnt::qasm::int::Error<'t>) -> Self {
         Self::Int(err.own())
@@ -58,6 +128,14 @@ impl fmt::Display for Error {
             Error::Lines(err) => write!(f, "{}", err),
             Error::Int(err) => write!(f, "{}", err),
             Error::Ast(err) => write!(f, "{}", err),
+            Error::Circuit(err) => write!(f, "{}", err),
+            Error::State(err) => write!(f, "{}", err),
+            Error::Memory(err) => write!(f, "{}", err),
+            Error::Sweep(err) => write!(f, "{}", err),
+            Error::ThreadPool(err) => write!(f, "Cannot build thread pool: {}", err),
+            Error::Transpile(err) => write!(f, "{}", err),
+            Error::Include(err) => write!(f, "{}", err),
+            Error::Assertion(failure) => write!(f, "{}", failure),
             Error::Inner => write!(f, "Inner functional error. {}", ON_UNEXPECTED),
             Error::Unimplemented => write!(f, "Unimplemented function. {}", ON_UNEXPECTED),
         }
@@ -74,11 +152,33 @@ impl std::error::Error for Error {}
 
 pub type Result<T = ()> = std::result::Result<T, Error>;
 
+/// Asks user the question and returns, whether it's answered positively.
+pub type Confirm = Box<dyn FnMut(&str) -> bool>;
+
 pub struct Process<'t> {
     head: Int<'t>,
     int: Int<'t>,
-    sym: Sym,
+    sim: Sim,
     storage: HashMap<PathBuf, Ast<'t>>,
+    include_path: include::SearchPath,
+    /// Included files by their canonical paths.
+    includes: HashMap<PathBuf, Ast<'t>>,
+    columns: usize,
+    limits: Limits,
+    confirm: Option<Confirm>,
+    confirmed_memory: u64,
+    timings: Timings,
+    report_timings: bool,
+    print_timings: bool,
+    debugger: Option<Debugger>,
+    watches: Vec<Watch>,
+    /// Lines with parameters, keyed by ASTs with parameters replaced, which are passed to `qvnt`.
+    symbolic: HashMap<usize, Ast<'t>>,
+    vars: Vec<(String, f64)>,
+    /// Session variables, which were in force, when tags were created.
+    tag_vars: HashMap<String, Vec<(String, f64)>>,
+    noise: Vec<noise::Rule>,
+    failed_assertions: usize,
 }
 
 fn combine_int<'t>(int0: Int<'t>, int1: &Int<'t>) -> Int<'t> {
@@ -86,13 +186,10 @@ fn combine_int<'t>(int0: Int<'t>, int1: &Int<'t>) -> Int<'t> {
 }
 
 impl<'t> Process<'t> {
-    fn ast_from_string(source: String) -> Result<Ast<'t>> {
+    pub fn ast_from_string(source: String) -> Result<Ast<'t>> {
         let source_leaked = drop_leakage::leak_string(source);
-        let ast = Ast::from_source(source_leaked).map_err(|err| {
-            unsafe {
-                drop_leakage::unleak_str(source_leaked);
-            }
-            err
+        let ast = Ast::from_source(source_leaked).inspect_err(|_| unsafe {
+            drop_leakage::unleak_str(source_leaked);
         })?;
         Ok(ast)
     }
@@ -100,46 +197,310 @@ impl<'t> Process<'t> {
     pub fn new(int: Int<'t>) -> Self {
         Self {
             head: Int::default(),
-            int: int.clone(),
-            sym: Sym::new(int),
+            int,
+            sim: Sim::default(),
             storage: HashMap::new(),
+            include_path: include::SearchPath::default(),
+            includes: HashMap::new(),
+            columns: 80,
+            limits: Limits::default(),
+            confirm: None,
+            confirmed_memory: 0,
+            timings: Timings::default(),
+            report_timings: false,
+            print_timings: false,
+            debugger: None,
+            watches: vec![],
+            symbolic: HashMap::new(),
+            vars: vec![],
+            tag_vars: HashMap::new(),
+            noise: vec![],
+            failed_assertions: 0,
         }
     }
 
+    pub fn set_columns(&mut self, columns: usize) {
+        self.columns = columns;
+    }
+
+    /// Sets directories to search include files in, besides the directory of the including file.
+    pub fn set_include_dirs(&mut self, dirs: Vec<PathBuf>) {
+        self.include_path = include::SearchPath::new(dirs);
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Simulation near the memory limit requires user's confirmation by `confirm`,
+    /// which is set in interactive mode.
+    pub fn set_confirm(&mut self, confirm: Option<Confirm>) {
+        self.confirm = confirm;
+    }
+
+    fn check_memory(&mut self, q_num: usize) -> Result {
+        self.check_memory_with(q_num, 0)
+    }
+
+    /// Checks memory as [`Process::check_memory`] does for simulation,
+    /// which keeps `copies` more copies of the state.
+    fn check_memory_with(&mut self, q_num: usize, copies: u64) -> Result {
+        let needed = memory::estimate_with(q_num, copies);
+        if self.limits.check(q_num, copies)? != memory::Verdict::NearLimit
+            || needed <= self.confirmed_memory
+        {
+            return Ok(());
+        }
+        if let Some(confirm) = &mut self.confirm {
+            let question = format!(
+                "Simulation of {} qubits needs {} of memory (limit is {}). Continue? [y/N] ",
+                q_num,
+                memory::format_size(needed),
+                memory::format_size(self.limits.max_memory),
+            );
+            if !confirm(&question) {
+                return Err(memory::Error::Declined(q_num).into());
+            }
+            self.confirmed_memory = needed;
+        }
+        Ok(())
+    }
+
+    /// Report timings of every processed line, not only of ones started with `:time`.
+    pub fn set_report_timings(&mut self, report_timings: bool) {
+        self.report_timings = report_timings;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.sim.set_seed(seed);
+    }
+
+    pub fn set_threads(&mut self, threads: NonZeroUsize) -> Result {
+        self.sim.set_threads(threads)?;
+        Ok(())
+    }
+
     pub fn int(&self) -> Int<'t> {
         let int = self.int.clone();
         unsafe { int.append_int(self.head.clone()) }
     }
 
+    /// Lowers the current program, including uncommitted changes.
+    pub fn circuit(&self, int_tree: &Tree<Int<'t>>) -> Result<Circuit> {
+        self.circuit_with(int_tree, &self.vars)
+    }
+
+    /// Lowers the current program with parameters `vars` bound.
+    fn circuit_with(&self, int_tree: &Tree<Int<'t>>, vars: &[(String, f64)]) -> Result<Circuit> {
+        let path = int_tree.path_to_head();
+        let asts = path.into_iter().chain([&self.head]).flat_map(Int::iter_ast);
+        Ok(Circuit::from_asts_with(
+            asts.map(|ast| self.symbolic_ast(ast)),
+            vars,
+        )?)
+    }
+
+    /// Checks, whether gate arguments of the current program refer to the variable `var`.
+    fn references(&self, int_tree: &Tree<Int<'t>>, var: &str) -> bool {
+        let path = int_tree.path_to_head();
+        let mut asts = path.into_iter().chain([&self.head]).flat_map(Int::iter_ast);
+        asts.any(|ast| params::references(self.symbolic_ast(ast), var))
+    }
+
+    /// Lowers the program, which is built from the tag `name` or, if there's no such tag, from the file `name`.
+    pub fn circuit_of(&mut self, int_tree: &Tree<Int<'t>>, name: &str) -> Result<Circuit> {
+        if int_tree.contains(name) {
+            let path = int_tree.path_to(name).ok_or(Error::Inner)?;
+            let vars = self.tag_vars.get(name).unwrap_or(&self.vars);
+            Ok(Circuit::from_asts_with(
+                path.into_iter()
+                    .flat_map(Int::iter_ast)
+                    .map(|ast| self.symbolic_ast(ast)),
+                vars,
+            )?)
+        } else {
+            let int = self.int_from_file(name.into())?;
+            Ok(Circuit::from_asts_with(
+                int.iter_ast().map(|ast| self.symbolic_ast(ast)),
+                &self.vars,
+            )?)
+        }
+    }
+
+    /// Restores parameters of the line, which were replaced to build the program with `qvnt`.
+    fn symbolic_ast<'a>(&'a self, ast: &'a Ast<'t>) -> &'a Ast<'t> {
+        self.symbolic.get(&params::key(ast)).unwrap_or(ast)
+    }
+
+    fn var(&self, name: &str) -> Option<f64> {
+        self.vars
+            .iter()
+            .find(|(var, _)| var == name)
+            .map(|(_, value)| *value)
+    }
+
+    fn set_var(&mut self, name: String, value: f64) {
+        match self.vars.iter_mut().find(|(var, _)| *var == name) {
+            Some((_, old)) => *old = value,
+            None => self.vars.push((name, value)),
+        }
+    }
+
+    fn var_values(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.vars.iter().map(|(var, value)| (var.as_str(), *value))
+    }
+
     fn reset(&mut self, int: Int<'t>) {
         self.head = Int::default();
         self.int = int;
     }
 
-    fn sym_update(&mut self) {
-        let int = self.int();
-        self.sym.init(int);
+    /// Prepares simulation of the circuit with noise rules resolved against its registers.
+    fn sim_init(&mut self, circuit: Circuit) -> Result {
+        self.check_memory(circuit.q_num())?;
+        let noise = self
+            .noise
+            .iter()
+            .map(|rule| rule.resolve(&circuit))
+            .collect::<circuit::Result<_>>()?;
+        self.sim.set_noise(noise);
+        self.sim.init(circuit)?;
+        Ok(())
     }
 
-    fn sym_go(&mut self) {
-        self.sym_update();
-        self.sym.reset();
-        self.sym.finish();
+    fn sim_update(&mut self, int_tree: &Tree<Int<'t>>) -> Result {
+        let start = Instant::now();
+        let circuit = self.circuit(int_tree)?;
+        self.sim_init(circuit)?;
+        self.timings.init += start.elapsed();
+        Ok(())
+    }
+
+    fn sim_go(&mut self, int_tree: &Tree<Int<'t>>) -> Result {
+        self.sim_update(int_tree)?;
+        let start = Instant::now();
+        self.sim.reset();
+        self.sim.finish();
+        self.timings.finish += start.elapsed();
+        Ok(())
+    }
+
+    pub fn failed_assertions(&self) -> usize {
+        self.failed_assertions
+    }
+
+    pub fn is_debugging(&self) -> bool {
+        self.debugger.is_some()
     }
 
     pub fn process(&mut self, int_set: &mut Tree<Int<'t>>, line: String) -> Result<bool> {
-        match line.parse::<Line>()? {
+        if let Some(debugger) = &mut self.debugger {
+            let cmd = line.parse::<debug::Command>().map_err(lines::Error::from)?;
+            if !debugger.process(&mut self.sim, cmd) {
+                self.debugger = None;
+            }
+            return Ok(true);
+        }
+
+        let start = Instant::now();
+        self.timings = Timings::default();
+        self.print_timings = self.report_timings;
+
+        let ret = match line.parse::<Line>()? {
             Line::Qasm => self.process_qasm(line).map(|_| true),
             Line::Commands(cmds) => self.process_cmd(int_set, cmds.into_iter()),
+        }?;
+
+        self.timings.total = start.elapsed();
+        if self.print_timings {
+            println!("{}", self.timings);
         }
+        Ok(ret)
     }
 
     pub fn process_qasm(&mut self, line: String) -> Result {
-        let ast = Self::ast_from_string(line)?;
+        let includes = self.include_asts(&line, None)?;
+        let start = Instant::now();
+        for include in includes {
+            if !self.contains_ast(&include) {
+                self.int.ast_changes(&mut self.head, include)?;
+            }
+        }
+        self.timings.build += start.elapsed();
+
+        let start = Instant::now();
+        let has_includes = !include::statements(&line).is_empty();
+        let ast = match Self::ast_from_string(line) {
+            Err(Error::Ast(owned_errors::ast::OwnedError::EmptySource)) if has_includes => {
+                return Ok(())
+            }
+            ast => ast?,
+        };
+        let ast = match self.bind_params(&ast) {
+            Ok(bound) => bound.unwrap_or(ast),
+            Err(err) => {
+                let source = ast.source();
+                std::mem::drop(ast);
+                unsafe { drop_leakage::unleak_str(source) };
+                return Err(err);
+            }
+        };
+        self.timings.parse += start.elapsed();
+
+        let start = Instant::now();
         self.int.ast_changes(&mut self.head, ast)?;
+        self.timings.build += start.elapsed();
         Ok(())
     }
 
+    /// Binds session variables in gate arguments of `ast`, remembering its symbolic form.
+    /// Returns `None`, if there are no variables in it.
+    fn bind_params(&mut self, ast: &Ast<'t>) -> Result<Option<Ast<'t>>> {
+        match params::bind(ast, &self.vars)? {
+            Some(bound) => {
+                let bound = Self::ast_from_string(bound)?;
+                self.symbolic.insert(params::key(&bound), ast.clone());
+                Ok(Some(bound))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Parses files, included by `source` of the file `path`, in order they should be added to the program.
+    fn include_asts(&mut self, source: &str, path: Option<&Path>) -> Result<Vec<Ast<'t>>> {
+        let mut asts = vec![];
+        for include in self.include_path.resolve(source, path)? {
+            if let Some(ast) = self.includes.get(&include.path) {
+                asts.push(ast.clone());
+                continue;
+            }
+            let start = Instant::now();
+            let ast = match Self::ast_from_string(include.source) {
+                Err(Error::Ast(owned_errors::ast::OwnedError::EmptySource)) => continue,
+                ast => ast?,
+            };
+            self.timings.parse += start.elapsed();
+            self.includes.insert(include.path, ast.clone());
+            asts.push(ast);
+        }
+        Ok(asts)
+    }
+
+    /// Checks the AST is the source of an included file.
+    pub fn is_include(&self, ast: &Ast<'t>) -> bool {
+        self.includes
+            .values()
+            .any(|include| std::ptr::eq(include.source(), ast.source()))
+    }
+
+    /// Checks the AST is already a part of the program.
+    fn contains_ast(&self, ast: &Ast<'t>) -> bool {
+        self.int
+            .iter_ast()
+            .chain(self.head.iter_ast())
+            .any(|other| std::ptr::eq(other.source(), ast.source()))
+    }
+
     pub fn process_cmd(
         &mut self,
         int_tree: &mut Tree<Int<'t>>,
@@ -148,25 +509,269 @@ impl<'t> Process<'t> {
         while let Some(cmd) = cmds.next() {
             match cmd {
                 Command::Loop(n) => {
-                    for _ in 0..n {
+                    let count = circuit::eval(&n, self.var_values())
+                        .ok()
+                        .filter(|count| count.fract() == 0.0 && *count >= 0.0)
+                        .ok_or(lines::Error::InvalidLoopCount(n))?;
+                    for _ in 0..count as usize {
                         self.process_cmd(int_tree, cmds.clone())?;
                     }
                     break;
                 }
                 Command::Tags(tag_cmd) => self.process_tag_cmd(int_tree, tag_cmd)?,
-                Command::Go => self.sym_go(),
+                Command::Go => self.sim_go(int_tree)?,
+                Command::Sample(shots) => {
+                    self.sim_update(int_tree)?;
+                    let start = Instant::now();
+                    let counts = self.sim.sample(shots);
+                    self.timings.finish += start.elapsed();
+                    print!("{}", counts);
+                }
+                Command::Debug => {
+                    self.sim_update(int_tree)?;
+                    self.sim.reset();
+                    let mut debugger = Debugger::new(self.circuit(int_tree)?);
+                    debugger.process(&mut self.sim, debug::Command::Where);
+                    self.debugger = Some(debugger);
+                }
+                Command::Let(name, expr) => {
+                    let value = circuit::eval(&expr, self.var_values())?;
+                    self.set_var(name, value);
+                }
+                Command::Minimize(minimize) => {
+                    let start = match &minimize.from {
+                        Some(from) => from.clone(),
+                        None => minimize
+                            .vars
+                            .iter()
+                            .map(|name| self.var(name).unwrap_or(0.0))
+                            .collect(),
+                    };
+                    let watch = Watch::Expect(minimize.obs.clone());
+                    let timer = Instant::now();
+                    let outcome =
+                        minimize.run(start, self.sim.seed(), |params| -> Result<f64> {
+                            let bound = minimize.vars.iter().cloned().zip(params.iter().cloned());
+                            let vars: Vec<_> = self.vars.iter().cloned().chain(bound).collect();
+                            let circuit = self.circuit_with(int_tree, &vars)?;
+                            self.sim_init(circuit.clone())?;
+                            self.sim.reset();
+                            self.sim.finish();
+                            Ok(watch.value(&circuit, self.sim.state())?)
+                        })?;
+                    self.timings.finish += timer.elapsed();
+                    println!("{}", outcome);
+                    for (name, value) in minimize.vars.into_iter().zip(outcome.params) {
+                        self.set_var(name, value);
+                    }
+                }
+                Command::Vars => {
+                    for (name, value) in &self.vars {
+                        println!("{name} = {value}");
+                    }
+                }
+                Command::Watch(Some(watch)) => self.watches.push(watch),
+                Command::Watch(None) => {
+                    for (idx, watch) in self.watches.iter().enumerate() {
+                        println!("{idx}: {watch}");
+                    }
+                }
+                Command::Unwatch => self.watches.clear(),
+                Command::Trace(path) => {
+                    self.sim_update(int_tree)?;
+                    let start = Instant::now();
+                    let trace =
+                        watch::trace(&self.circuit(int_tree)?, &mut self.sim, &self.watches)?;
+                    self.timings.finish += start.elapsed();
+                    match path {
+                        Some(path) => std::fs::write(path, trace.to_csv())?,
+                        None => print!("{}", trace),
+                    }
+                }
+                Command::Sweep(sweep, path) => {
+                    if let Some(range) = sweep
+                        .ranges
+                        .iter()
+                        .find(|range| !self.references(int_tree, &range.var))
+                    {
+                        return Err(sweep::Error::UnusedParameter(range.var.clone()).into());
+                    }
+                    let start = Instant::now();
+                    let table = sweep.run(|vars| -> Result<f64> {
+                        let vars: Vec<_> = self.vars.iter().chain(vars).cloned().collect();
+                        let circuit = self.circuit_with(int_tree, &vars)?;
+                        self.sim_init(circuit.clone())?;
+                        self.sim.reset();
+                        self.sim.finish();
+                        Ok(sweep.watch.value(&circuit, self.sim.state())?)
+                    })?;
+                    self.timings.finish += start.elapsed();
+                    match path {
+                        Some(path) => std::fs::write(path, table.to_csv())?,
+                        None => print!("{}", table),
+                    }
+                }
+                Command::Assert(assertion) => {
+                    self.sim_update(int_tree)?;
+                    let circuit = self.circuit(int_tree)?;
+                    if let assertion::Assertion::Dist { .. } = assertion {
+                        // the state is kept aside while the circuit is re-run
+                        self.check_memory_with(circuit.q_num(), 1)?;
+                    }
+                    if let Some(failure) = assertion.check(&circuit, &mut self.sim)? {
+                        self.failed_assertions += 1;
+                        return Err(Error::Assertion(failure));
+                    }
+                }
+                Command::Time => self.print_timings = true,
+                Command::Profile => {
+                    self.sim_update(int_tree)?;
+                    let start = Instant::now();
+                    self.sim.reset();
+                    let profile = self.sim.profile();
+                    self.timings.finish += start.elapsed();
+                    println!("{}", profile);
+                }
+                Command::Noise(Some(rule)) => {
+                    self.noise
+                        .retain(|old| (old.channel, &old.target) != (rule.channel, &rule.target));
+                    self.noise.push(rule);
+                }
+                Command::Noise(None) => self.noise.clear(),
+                Command::Seed(Some(seed)) => self.set_seed(seed),
+                Command::Seed(None) => println!("Seed: {}", self.sim.seed()),
+                Command::Set(lines::Setting::Threads(threads)) => self.set_threads(threads)?,
+                Command::Status => self.print_status(int_tree)?,
+                Command::Equiv(lhs, rhs) => {
+                    let lhs = self.circuit_of(int_tree, &lhs)?;
+                    let rhs = self.circuit_of(int_tree, &rhs)?;
+                    if lhs.q_num() > equiv::EXACT_MAX_QUBITS {
+                        self.check_memory(lhs.q_num().max(rhs.q_num()))?;
+                    }
+                    println!("{}", equiv::check(&lhs, &rhs)?);
+                }
+                Command::Draw(tag) => {
+                    let circuit = match tag {
+                        Some(tag) => self.circuit_of(int_tree, &tag)?,
+                        None => self.circuit(int_tree)?,
+                    };
+                    print!("{}", draw::render(&circuit, self.columns));
+                }
+                Command::Lib(None) => print!("{}", library::list()),
+                Command::Lib(Some((template, target))) => {
+                    let size = match target.parse::<usize>() {
+                        Ok(size) => size,
+                        Err(_) => self
+                            .circuit(int_tree)?
+                            .q_regs
+                            .iter()
+                            .find(|reg| reg.name == target)
+                            .map(|reg| reg.size)
+                            .ok_or(circuit::Error::UnknownReg(target))?,
+                    };
+                    let gate = template.define(size).map_err(lines::Error::Library)?;
+                    self.process_qasm(gate)?;
+                    println!("Defined gate {}", template.signature(size));
+                }
+                Command::Lint => {
+                    let path = int_tree.path_to_head();
+                    let asts = path.into_iter().chain([&self.head]).flat_map(Int::iter_ast);
+                    let warnings = lint::lint(
+                        asts.filter(|ast| !self.is_include(ast))
+                            .map(|ast| self.symbolic_ast(ast)),
+                    )?;
+                    for warning in &warnings {
+                        println!("{}", warning);
+                    }
+                    if warnings.is_empty() {
+                        println!("No warnings");
+                    }
+                }
+                Command::Resources(tag) => {
+                    let circuit = match tag {
+                        Some(tag) => self.circuit_of(int_tree, &tag)?,
+                        None => self.circuit(int_tree)?,
+                    };
+                    println!("{}", Resources::of(&circuit));
+                }
+                Command::Optimize(tag, commit) => {
+                    let circuit = match tag {
+                        Some(tag) => self.circuit_of(int_tree, &tag)?,
+                        None => self.circuit(int_tree)?,
+                    };
+                    let (optimized, report) = optimize::optimize(&circuit);
+                    println!("{}", report);
+                    if let Some(new_tag) = commit {
+                        self.commit_circuit(int_tree, new_tag, &optimized)?;
+                    }
+                }
+                Command::Transpile(basis, tag, output) => {
+                    let circuit = match tag {
+                        Some(tag) => self.circuit_of(int_tree, &tag)?,
+                        None => self.circuit(int_tree)?,
+                    };
+                    let transpiled = basis.transpile(&circuit)?;
+                    let source = transpiled.to_qasm()?;
+                    match output {
+                        Some(path) => std::fs::write(path, source)?,
+                        None => print!("{}", source),
+                    }
+                    if circuit.q_num() > equiv::EXACT_MAX_QUBITS {
+                        println!(
+                            "Not verified: only circuits up to {} qubits are simulated",
+                            equiv::EXACT_MAX_QUBITS
+                        );
+                    } else {
+                        match equiv::check(&circuit, &transpiled) {
+                            Ok(report) => println!("{}", report),
+                            Err(err) => println!("Not verified: {}", err),
+                        }
+                    }
+                }
+                Command::Export(format, path, tag) => {
+                    let circuit = match tag {
+                        Some(tag) => self.circuit_of(int_tree, &tag)?,
+                        None => self.circuit(int_tree)?,
+                    };
+                    std::fs::write(path, export::render(&circuit, format))?;
+                }
                 Command::Load(path) => self.load_qasm(int_tree, path, true)?,
+                Command::Init(regs) => {
+                    let circuit = self.circuit(int_tree)?;
+                    let source = regs
+                        .iter()
+                        .map(|(name, value)| circuit.init_source(name, value))
+                        .collect::<circuit::Result<String>>()?;
+                    self.process_qasm(source)?;
+                }
+                Command::SaveState(path) => {
+                    self.sim_update(int_tree)?;
+                    std::fs::write(&path, state::serialize(&path, self.sim.state()))?;
+                }
+                Command::LoadState(path) => {
+                    let initial = match path {
+                        Some(path) => {
+                            let q_num = self.circuit(int_tree)?.q_num();
+                            self.check_memory(q_num)?;
+                            let psi = state::deserialize(&path, &std::fs::read(&path)?)?;
+                            state::validate(&psi, q_num)?;
+                            Some(psi)
+                        }
+                        None => None,
+                    };
+                    self.sim.set_initial(initial);
+                }
                 Command::Class => {
-                    self.sym_update();
-                    println!("CReg: {}", self.sym.get_class().get());
+                    self.sim_update(int_tree)?;
+                    println!("CReg: {}", self.sim.get_class());
                 }
                 Command::Polar => {
-                    self.sym_update();
-                    println!("QReg polar: {:.4?}", self.sym.get_polar_wavefunction());
+                    self.sim_update(int_tree)?;
+                    println!("QReg polar: {:.4?}", self.sim.get_polar_wavefunction());
                 }
                 Command::Probs => {
-                    self.sym_update();
-                    println!("QReg probabilities: {:.4?}", self.sym.get_probabilities());
+                    self.sim_update(int_tree)?;
+                    println!("QReg probabilities: {:.4?}", self.sim.get_probabilities());
                 }
                 Command::Ops => {
                     println!("Operations: {}", self.int().get_ops_tree());
@@ -185,6 +790,31 @@ impl<'t> Process<'t> {
         Ok(true)
     }
 
+    fn print_status(&self, int_tree: &Tree<Int<'t>>) -> Result {
+        let circuit = self.circuit(int_tree)?;
+        let threads = match self.sim.threads() {
+            1 => "1 (single-threaded)".to_string(),
+            threads => threads.to_string(),
+        };
+        println!("Qubits:  {}", circuit.q_num());
+        println!("Bits:    {}", circuit.c_num());
+        println!(
+            "Memory:  {} (limit {})",
+            memory::format_size(memory::estimate(circuit.q_num())),
+            memory::format_size(self.limits.max_memory)
+        );
+        println!("Threads: {}", threads);
+        println!("Seed:    {}", self.sim.seed());
+        match self.noise.is_empty() {
+            true => println!("Noise:   off"),
+            false => {
+                let rules: Vec<_> = self.noise.iter().map(noise::Rule::to_string).collect();
+                println!("Noise:   {}", rules.join(", "));
+            }
+        }
+        Ok(())
+    }
+
     pub fn process_tag_cmd(
         &mut self,
         int_tree: &mut Tree<Int<'t>>,
@@ -199,6 +829,7 @@ impl<'t> Process<'t> {
                 if !int_tree.commit(&tag, self.head.clone()) {
                     return Err(lines::Error::ExistedTagName(tag).into());
                 } else {
+                    self.tag_vars.insert(tag, self.vars.clone());
                     unsafe {
                         self.int = self.int.clone().append_int(std::mem::take(&mut self.head))
                     };
@@ -207,7 +838,9 @@ impl<'t> Process<'t> {
             Command::Remove(tag) => {
                 use crate::int_tree::RemoveStatus::*;
                 match int_tree.remove(&tag) {
-                    Removed => {}
+                    Removed => {
+                        self.tag_vars.remove(&tag);
+                    }
                     NotFound => return Err(lines::Error::WrongTagName(tag).into()),
                     IsParent => return Err(lines::Error::TagIsParent(tag).into()),
                     IsHead => return Err(lines::Error::TagIsHead(tag).into()),
@@ -218,10 +851,10 @@ impl<'t> Process<'t> {
                 if !int_tree.checkout(&tag) {
                     return Err(lines::Error::WrongTagName(tag).into());
                 } else {
-                    let new_int = int_tree
-                        .collect_to_head(Int::default, combine_int)
-                        .ok_or(Error::Inner)?;
-                    self.reset(new_int);
+                    if let Some(vars) = self.tag_vars.get(&tag) {
+                        self.vars = vars.clone();
+                    }
+                    self.checkout_head(int_tree)?;
                 }
             }
             Command::Root => {
@@ -235,6 +868,34 @@ impl<'t> Process<'t> {
         Ok(())
     }
 
+    fn ast_from_file(&mut self, path: PathBuf) -> Result<Ast<'t>> {
+        match self.storage.entry(path) {
+            std::collections::hash_map::Entry::Occupied(ast) => Ok(ast.get().clone()),
+            std::collections::hash_map::Entry::Vacant(empty) => {
+                let source = std::fs::read_to_string(empty.key())?;
+                let start = Instant::now();
+                let ast = Self::ast_from_string(source)?;
+                self.timings.parse += start.elapsed();
+                Ok(empty.insert(ast).clone())
+            }
+        }
+    }
+
+    /// Builds the program from the file, preceded by files it includes.
+    pub fn int_from_file(&mut self, path: PathBuf) -> Result<Int<'t>> {
+        let ast = self.ast_from_file(path.clone())?;
+        let includes = self.include_asts(ast.source(), Some(&path))?;
+        let ast = self.bind_params(&ast)?.unwrap_or(ast);
+
+        let start = Instant::now();
+        let mut int = Int::default();
+        for ast in includes.into_iter().chain([ast]) {
+            int.add_ast(ast)?;
+        }
+        self.timings.build += start.elapsed();
+        Ok(int)
+    }
+
     pub fn load_qasm(
         &mut self,
         int_tree: &mut Tree<Int<'t>>,
@@ -242,24 +903,54 @@ impl<'t> Process<'t> {
         switch_to: bool,
     ) -> Result {
         let path_tag = format!("{}", path.display());
-
-        let ast = match self.storage.entry(path) {
-            std::collections::hash_map::Entry::Occupied(ast) => ast.get().clone(),
-            std::collections::hash_map::Entry::Vacant(empty) => {
-                let source = std::fs::read_to_string(empty.key())?;
-                let ast = Self::ast_from_string(source)?;
-                empty.insert(ast).clone()
-            }
-        };
+        let int = self.int_from_file(path)?;
 
         int_tree.checkout_root();
-        if !int_tree.commit(&path_tag, Int::new(ast)?) {
+        if !int_tree.commit(&path_tag, int) {
             return Err(Error::Inner);
         }
-        if !switch_to {
+        self.tag_vars.insert(path_tag, self.vars.clone());
+        if switch_to {
+            self.checkout_head(int_tree)?;
+        } else {
             int_tree.checkout_root();
         }
 
         Ok(())
     }
+
+    /// Saves the circuit as a new tag from the root and switches to it.
+    fn commit_circuit(
+        &mut self,
+        int_tree: &mut Tree<Int<'t>>,
+        tag: String,
+        circuit: &Circuit,
+    ) -> Result {
+        if int_tree.contains(&tag) {
+            return Err(lines::Error::ExistedTagName(tag).into());
+        }
+        let ast = Self::ast_from_string(circuit.to_qasm()?)?;
+
+        let start = Instant::now();
+        let int = Int::new(ast)?;
+        self.timings.build += start.elapsed();
+
+        int_tree.checkout_root();
+        if !int_tree.commit(&tag, int) {
+            return Err(Error::Inner);
+        }
+        self.tag_vars.insert(tag, self.vars.clone());
+        self.checkout_head(int_tree)
+    }
+
+    /// Rebuilds the program from the tag, which is currently the head, dropping uncommitted changes.
+    fn checkout_head(&mut self, int_tree: &Tree<Int<'t>>) -> Result {
+        let start = Instant::now();
+        let new_int = int_tree
+            .collect_to_head(Int::default, combine_int)
+            .ok_or(Error::Inner)?;
+        self.timings.build += start.elapsed();
+        self.reset(new_int);
+        Ok(())
+    }
 }
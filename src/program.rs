@@ -1,11 +1,12 @@
-use std::{fmt, path::PathBuf};
+use std::{cell::RefCell, fmt, io::IsTerminal, num::NonZeroUsize, path::PathBuf, rc::Rc};
 
 use qvnt::prelude::Int;
-use rustyline::{error::ReadlineError, Config, Editor, history::FileHistory};
+use rustyline::{config::Configurer, error::ReadlineError, history::FileHistory, Config, Editor};
 
 use crate::{
     cli::CliArgs,
     int_tree::Tree,
+    memory::{self, Limits},
     process::{self, Process},
 };
 
@@ -18,6 +19,9 @@ pub enum ProgramError {
     HistoryPath,
     Process(process::Error),
     Readline(ReadlineError),
+    FailedAssertions(usize),
+    FailedTests(usize),
+    Unformatted(PathBuf),
 }
 
 impl From<process::Error> for ProgramError {
@@ -38,6 +42,9 @@ impl fmt::Display for ProgramError {
             ProgramError::HistoryPath => write!(f, "Cannot find HOME or CWD"),
             ProgramError::Process(err) => write!(f, "Process error: {}", err),
             ProgramError::Readline(err) => write!(f, "Readline error: {}", err),
+            ProgramError::FailedAssertions(count) => write!(f, "{} assertions failed", count),
+            ProgramError::FailedTests(count) => write!(f, "{} tests failed", count),
+            ProgramError::Unformatted(path) => write!(f, "{} is not formatted", path.display()),
         }
     }
 }
@@ -48,6 +55,9 @@ impl ProgramError {
             ProgramError::HistoryPath => false,
             ProgramError::Process(err) => err.should_echo(),
             ProgramError::Readline(err) => !matches!(err, ReadlineError::Interrupted),
+            ProgramError::FailedAssertions(_)
+            | ProgramError::FailedTests(_)
+            | ProgramError::Unformatted(_) => true,
         }
     }
 
@@ -63,17 +73,52 @@ impl ProgramError {
     }
 }
 
+/// Creates the process with simulation settings from command line arguments.
+pub fn new_process<'t>(cli: &CliArgs) -> ProgramResult<Process<'t>> {
+    let mut process = Process::new(Int::default());
+    if let Some(seed) = cli.seed {
+        process.set_seed(seed);
+    }
+    let threads = cli
+        .threads
+        .or_else(|| std::thread::available_parallelism().ok())
+        .unwrap_or(NonZeroUsize::MIN);
+    process.set_threads(threads)?;
+    process.set_limits(Limits {
+        max_memory: cli
+            .max_memory
+            .or_else(memory::available)
+            .unwrap_or(memory::DEFAULT_MAX_MEMORY),
+        max_qubits: cli.max_qubits,
+    });
+    process.set_report_timings(cli.timings);
+    process.set_include_dirs(cli.include_dirs.clone());
+    Ok(process)
+}
+
+type Interact = Editor<(), FileHistory>;
+
+/// Reads the answer to `question` by the line editor without adding it to the history.
+fn confirm(interact: &mut Interact, question: &str) -> bool {
+    interact.set_auto_add_history(false);
+    let answer = interact.readline(question);
+    interact.set_auto_add_history(true);
+    matches!(answer.as_deref().map(str::trim), Ok("y" | "Y" | "yes"))
+}
+
 pub struct Program<'t> {
     pub history: PathBuf,
     pub inputs: Vec<PathBuf>,
-    pub interact: Editor<(), FileHistory>,
+    /// Line editor, which is shared with the process to ask for confirmations.
+    pub interact: Rc<RefCell<Interact>>,
     pub curr_process: Process<'t>,
     pub int_tree: Tree<Int<'t>>,
+    pub interactive: bool,
 }
 
 impl<'t> Program<'t> {
-    pub fn new() -> ProgramResult<Self> {
-        let cli = CliArgs::new();
+    pub fn new(cli: CliArgs) -> ProgramResult<Self> {
+        let mut curr_process = new_process(&cli)?;
 
         #[cfg(feature = "tracing")]
         if let Some(logs_path) = cli.logs_enabled {
@@ -107,12 +152,22 @@ impl<'t> Program<'t> {
             .check_cursor_position(true)
             .build();
 
+        let interactive = std::io::stdin().is_terminal();
+        let interact = Rc::new(RefCell::new(Editor::with_config(config)?));
+        if interactive {
+            let interact = Rc::clone(&interact);
+            curr_process.set_confirm(Some(Box::new(move |question| {
+                confirm(&mut interact.borrow_mut(), question)
+            })));
+        }
+
         Ok(Self {
             history,
             inputs: cli.inputs,
-            interact: Editor::with_config(config)?,
-            curr_process: Process::new(Int::default()),
+            interact,
+            curr_process,
             int_tree: Tree::with_root(ROOT_TAG),
+            interactive,
         })
     }
 
@@ -129,11 +184,24 @@ impl<'t> Program<'t> {
 
         const SIGN: &str = "|Q> ";
         const BLCK: &str = "... ";
+        const DBG: &str = "(debug) ";
 
         let mut block = (false, String::new());
         loop {
-            let maybe_result = match self.interact.readline(if block.0 { BLCK } else { SIGN }) {
+            if let Some((columns, _)) = self.interact.borrow_mut().dimensions() {
+                self.curr_process.set_columns(columns);
+            }
+            let prompt = if block.0 {
+                BLCK
+            } else if self.curr_process.is_debugging() {
+                DBG
+            } else {
+                SIGN
+            };
+            let line = self.interact.borrow_mut().readline(prompt);
+            let maybe_result = match line {
                 Ok(line) => self.process_line(&mut block, line),
+                Err(ReadlineError::Eof) => Some(Ok(())),
                 Err(err) => Self::decorate_error(Err(err)),
             };
 
@@ -200,15 +268,20 @@ impl<'t> Program<'t> {
         const PROLOGUE: &str = "QVNT - Interactive QASM Interpreter";
         print!("{}\n\n", PROLOGUE);
 
-        if let Err(err) = self.interact.load_history(&self.history) {
+        if let Err(err) = self.interact.borrow_mut().load_history(&self.history) {
             log::error!(target: "qvnt_i::main", "History not loaded: {}", err);
         }
         let ret_code = self.loop_fn();
-        if let Err(err) = self.interact.save_history(&self.history) {
+        if let Err(err) = self.interact.borrow_mut().save_history(&self.history) {
             log::error!(target: "qvnt_i::main", "History not saved: {}", err);
         }
 
-        ret_code
+        match self.curr_process.failed_assertions() {
+            failed if failed > 0 && !self.interactive && ret_code.is_ok() => {
+                Err(ProgramError::FailedAssertions(failed))
+            }
+            _ => ret_code,
+        }
     }
 }
 
@@ -218,7 +291,8 @@ mod tests {
 
     #[test]
     fn main_loop() {
-        let mut program = Program::new().unwrap();
+        let cli = <CliArgs as clap::Parser>::parse_from(["qvnt-i"]);
+        let mut program = Program::new(cli).unwrap();
         let mut block = (false, String::new());
 
         let input = vec![
@@ -243,4 +317,31 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn load_params() {
+        let cli = <CliArgs as clap::Parser>::parse_from(["qvnt-i"]);
+        let mut program = Program::new(cli).unwrap();
+        let path = std::env::temp_dir().join(format!("qvnt-i-params-{}.qasm", std::process::id()));
+        std::fs::write(&path, "qreg q[1]; rx(theta) q[0];").unwrap();
+        let mut process = |line: &str| {
+            program
+                .curr_process
+                .process(&mut program.int_tree, line.to_string())
+        };
+
+        let load = format!(":load {}", path.display());
+        assert!(process(&load).is_err());
+        assert!(process(":let theta = pi/2").is_ok());
+        assert!(process(&load).is_ok());
+        std::fs::remove_file(&path).unwrap();
+
+        // parameters of the loaded file follow the variable
+        assert!(process(":let theta = pi").is_ok());
+        let circuit = program.curr_process.circuit(&program.int_tree).unwrap();
+        match circuit.ops.as_slice() {
+            [crate::circuit::Op::Gate(gate)] => assert_eq!(gate.args, [std::f64::consts::PI]),
+            ops => panic!("unexpected ops: {:?}", ops),
+        }
+    }
 }
@@ -0,0 +1,260 @@
+use std::{fmt, time::Duration};
+
+use crate::{
+    circuit::{self, Circuit, Gate, Op, Register},
+    memory,
+};
+
+/// Rough time of updating a single amplitude by a single gate.
+const NS_PER_AMPLITUDE: f64 = 1.5;
+
+/// Gates, which are applied to each qubit of register separately.
+const SINGLE_QUBIT_GATES: &[&str] = &[
+    "x", "y", "z", "s", "sdg", "t", "tdg", "h", "rx", "ry", "rz", "u1", "u2", "u3",
+];
+
+/// Static resources of the circuit, collected without simulation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resources {
+    pub q_regs: Vec<Register>,
+    pub c_regs: Vec<Register>,
+    /// Number of applications of every gate, with macros expanded.
+    pub gates: Vec<(String, usize)>,
+    pub two_qubit: usize,
+    pub multi_qubit: usize,
+    pub t_count: usize,
+    pub depth: usize,
+    pub measurements: usize,
+    pub if_blocks: usize,
+    /// Number of operators, which simulation applies to the wavefunction.
+    pub applications: usize,
+}
+
+impl Resources {
+    pub fn of(circuit: &Circuit) -> Self {
+        let mut resources = Self {
+            q_regs: circuit.q_regs.clone(),
+            c_regs: circuit.c_regs.clone(),
+            ..Self::default()
+        };
+        let mut levels = vec![0; circuit.q_num() + circuit.c_num()];
+        for op in &circuit.ops {
+            resources.add_op(&mut levels, circuit.q_num(), op, 0);
+        }
+        resources.depth = levels.into_iter().max().unwrap_or(0);
+        resources
+    }
+
+    pub fn q_num(&self) -> usize {
+        self.q_regs.iter().map(|reg| reg.size).sum()
+    }
+
+    pub fn c_num(&self) -> usize {
+        self.c_regs.iter().map(|reg| reg.size).sum()
+    }
+
+    pub fn gate_count(&self) -> usize {
+        self.gates.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn memory(&self) -> u64 {
+        memory::estimate(self.q_num())
+    }
+
+    /// Rough single-threaded simulation time.
+    pub fn runtime(&self) -> Duration {
+        let amplitudes = (1_u64 << self.q_num().min(63)) as f64;
+        Duration::from_secs_f64(self.applications as f64 * amplitudes * NS_PER_AMPLITUDE * 1e-9)
+    }
+
+    fn count_gate(&mut self, name: &str, count: usize) {
+        match self.gates.iter_mut().find(|(gate, _)| gate == name) {
+            Some((_, total)) => *total += count,
+            None => self.gates.push((name.to_string(), count)),
+        }
+    }
+
+    fn add_gate(&mut self, gate: &Gate) {
+        let act_on = gate.act_on();
+        let (ctrl, base) = gate.controls();
+        let count = if ctrl == 0 && SINGLE_QUBIT_GATES.contains(&base.to_lowercase().as_str()) {
+            act_on.count_ones() as usize
+        } else {
+            match act_on.count_ones() {
+                0 | 1 => {}
+                2 => self.two_qubit += 1,
+                _ => self.multi_qubit += 1,
+            }
+            1
+        };
+        if ctrl == 0 && matches!(base.to_lowercase().as_str(), "t" | "tdg") {
+            self.t_count += count;
+        }
+        self.count_gate(&gate.name, count);
+    }
+
+    /// Accounts the instruction, conditioned by bits `cond`.
+    fn add_op(&mut self, levels: &mut [usize], q_num: usize, op: &Op, cond: usize) {
+        match op {
+            Op::Macro(_, ops) => {
+                for op in ops {
+                    self.add_op(levels, q_num, op, cond);
+                }
+                return;
+            }
+            Op::If(c_mask, _, op) => {
+                self.if_blocks += 1;
+                self.add_op(levels, q_num, op, cond | c_mask);
+                return;
+            }
+            Op::Gate(gate) => {
+                self.applications += 1;
+                self.add_gate(gate);
+            }
+            Op::Measure(q_mask, _) => {
+                self.applications += 1;
+                self.measurements += q_mask.count_ones() as usize;
+            }
+            Op::Reset(_) => {
+                self.applications += 1;
+                self.count_gate("reset", 1);
+            }
+            Op::Barrier(_) => {}
+        }
+
+        let rows: Vec<usize> = circuit::bits(op.act_on())
+            .chain(circuit::bits(cond).map(|bit| q_num + bit))
+            .chain(match op {
+                Op::Measure(_, c_mask) => circuit::bits(*c_mask).map(|bit| q_num + bit).collect(),
+                _ => vec![],
+            })
+            .collect();
+        let level = rows.iter().map(|&row| levels[row]).max().unwrap_or(0);
+        let level = match op {
+            Op::Barrier(_) => level,
+            _ => level + 1,
+        };
+        for row in rows {
+            levels[row] = level;
+        }
+    }
+}
+
+fn fmt_regs(regs: &[Register]) -> String {
+    regs.iter()
+        .map(|reg| format!("{}[{}]", reg.name, reg.size))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats the duration with the largest unit, which keeps the value at least 1.
+fn format_duration(duration: Duration) -> String {
+    const UNITS: &[(&str, f64)] = &[
+        ("ns", 1.0),
+        ("µs", 1e3),
+        ("ms", 1e6),
+        ("s", 1e9),
+        ("min", 60e9),
+        ("h", 3600e9),
+    ];
+    let nanos = duration.as_nanos() as f64;
+    let (unit, scale) = UNITS
+        .iter()
+        .rev()
+        .find(|(_, scale)| nanos >= *scale)
+        .unwrap_or(&UNITS[0]);
+    let value = nanos / scale;
+    if value < 10.0 {
+        let value = format!("{value:.1}");
+        format!("{} {unit}", value.trim_end_matches(".0"))
+    } else {
+        format!("{value:.0} {unit}")
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Qubits:       {} ({})",
+            self.q_num(),
+            fmt_regs(&self.q_regs)
+        )?;
+        writeln!(
+            f,
+            "Bits:         {} ({})",
+            self.c_num(),
+            fmt_regs(&self.c_regs)
+        )?;
+        writeln!(
+            f,
+            "Gates:        {} ({} two-qubit, {} multi-qubit, T-count {})",
+            self.gate_count(),
+            self.two_qubit,
+            self.multi_qubit,
+            self.t_count
+        )?;
+        let width = self
+            .gates
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (name, count) in &self.gates {
+            writeln!(f, "    {name:<width$}  {count}")?;
+        }
+        writeln!(f, "Depth:        {}", self.depth)?;
+        writeln!(f, "Measurements: {}", self.measurements)?;
+        writeln!(f, "If blocks:    {}", self.if_blocks)?;
+        writeln!(f, "Memory:       {}", memory::format_size(self.memory()))?;
+        write!(
+            f,
+            "Runtime:      ~{} (single thread)",
+            format_duration(self.runtime())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn count() {
+        let source = "qreg q[3]; creg c[3];
+            gate foo a, b { t a; cx a, b; tdg b; }
+            h q; foo q[0], q[1]; ccx q[0], q[1], q[2];
+            barrier q; measure q -> c; if(c==1) x q[2];";
+        let circuit = circuit(source);
+        let resources = Resources::of(&circuit);
+
+        assert_eq!(
+            resources.gates,
+            vec![
+                ("h".to_string(), 3),
+                ("t".to_string(), 1),
+                ("cx".to_string(), 1),
+                ("tdg".to_string(), 1),
+                ("ccx".to_string(), 1),
+                ("x".to_string(), 1),
+            ]
+        );
+        assert_eq!(resources.two_qubit, 1);
+        assert_eq!(resources.multi_qubit, 1);
+        assert_eq!(resources.t_count, 2);
+        assert_eq!(resources.measurements, 3);
+        assert_eq!(resources.if_blocks, 1);
+        // h, t, cx, tdg, ccx, measure, if
+        assert_eq!(resources.depth, 7);
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0 ns");
+        assert_eq!(format_duration(Duration::from_nanos(18)), "18 ns");
+        assert_eq!(format_duration(Duration::from_nanos(1_200)), "1.2 µs");
+        assert_eq!(format_duration(Duration::from_millis(3)), "3 ms");
+        assert_eq!(format_duration(Duration::from_secs(150)), "2.5 min");
+    }
+}
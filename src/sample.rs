@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::circuit::Op;
+
+/// Reason to re-simulate the circuit for every shot instead of sampling from the final state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    MidCircuitMeasurement,
+    Reset,
+    If,
+    Noise,
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fallback::MidCircuitMeasurement => write!(f, "circuit has mid-circuit measurements"),
+            Fallback::Reset => write!(f, "circuit has resets"),
+            Fallback::If => write!(f, "circuit has `if` blocks"),
+            Fallback::Noise => write!(f, "noise is enabled"),
+        }
+    }
+}
+
+/// Finds the start of trailing measurements, so the state before them is the same for every shot.
+/// Barriers are allowed among the measurements.
+pub fn terminal(ops: &[Op]) -> Result<usize, Fallback> {
+    let start = ops
+        .iter()
+        .rposition(|op| !matches!(op, Op::Measure(..) | Op::Barrier(_)))
+        .map_or(0, |idx| idx + 1);
+    for op in &ops[..start] {
+        match op {
+            Op::Measure(..) => return Err(Fallback::MidCircuitMeasurement),
+            Op::Reset(_) => return Err(Fallback::Reset),
+            Op::If(..) => return Err(Fallback::If),
+            Op::Gate(_) | Op::Macro(..) | Op::Barrier(_) => {}
+        }
+    }
+    Ok(start)
+}
+
+/// Numbers of shots, which ended with each value of classical registers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counts {
+    c_num: usize,
+    shots: usize,
+    counts: BTreeMap<usize, usize>,
+    fallback: Option<Fallback>,
+}
+
+impl Counts {
+    pub fn new(c_num: usize, fallback: Option<Fallback>) -> Self {
+        Self {
+            c_num,
+            shots: 0,
+            counts: BTreeMap::new(),
+            fallback,
+        }
+    }
+
+    pub fn add(&mut self, class: usize) {
+        self.shots += 1;
+        *self.counts.entry(class).or_default() += 1;
+    }
+
+    /// Outcomes with non-zero counts in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.counts.iter().map(|(class, count)| (*class, *count))
+    }
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.fallback {
+            None => writeln!(f, "Sampled {} shots from a single run", self.shots)?,
+            Some(fallback) => writeln!(f, "Re-simulated {} shots: {}", self.shots, fallback)?,
+        }
+        let width = self.c_num.max(7);
+        writeln!(
+            f,
+            "{:>width$}  {:>8}  {:>11}",
+            "outcome", "count", "probability"
+        )?;
+        for (class, count) in self.iter() {
+            let bits = match self.c_num {
+                0 => String::new(),
+                c_num => format!("{class:0c_num$b}"),
+            };
+            let prob = count as f64 / self.shots as f64;
+            writeln!(f, "{bits:>width$}  {count:>8}  {prob:>11.4}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::Gate;
+
+    #[test]
+    fn terminal_measurements() {
+        let h = Op::Gate(Gate {
+            name: "h".to_string(),
+            regs: vec![0b1],
+            args: vec![],
+        });
+        let measure = Op::Measure(0b1, 0b1);
+        assert_eq!(
+            terminal(&[h.clone(), Op::Barrier(0b1), measure.clone()]),
+            Ok(1)
+        );
+        assert_eq!(terminal(&[]), Ok(0));
+        assert_eq!(
+            terminal(&[measure.clone(), h.clone(), measure]),
+            Err(Fallback::MidCircuitMeasurement)
+        );
+        assert_eq!(
+            terminal(&[Op::If(0b1, 1, Box::new(h.clone())), h]),
+            Err(Fallback::If)
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use std::{num::NonZeroUsize, sync::Arc, time::Instant};
+
+use num_complex::Complex64 as C;
+use qvnt::prelude::{op, Applicable, MultiOp};
+use rand::{distributions::WeightedIndex, prelude::*, rngs::StdRng};
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::{
+    circuit::{self, Circuit, Op},
+    noise::{Channel, Noise},
+    sample::{self, Counts, Fallback},
+    state,
+    timings::Profile,
+};
+
+/// Minimal length of the wavefunction buffer, which `qvnt` operators can be applied to.
+const MIN_BUFFER_LEN: usize = 8;
+
+/// State vector simulator of the lowered [`Circuit`].
+///
+/// In contrast to [`qvnt::qasm::Sym`] it can start from an arbitrary initial state.
+#[derive(Clone, Debug)]
+pub struct Sim {
+    circuit: Circuit,
+    initial: Option<Vec<C>>,
+    psi: Vec<C>,
+    class: usize,
+    seed: u64,
+    rng: StdRng,
+    pool: Option<Arc<ThreadPool>>,
+    noise: Vec<Noise>,
+}
+
+impl Default for Sim {
+    fn default() -> Self {
+        let seed = rand::random();
+        let mut sim = Self {
+            circuit: Circuit::default(),
+            initial: None,
+            psi: vec![],
+            class: 0,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            pool: None,
+            noise: vec![],
+        };
+        sim.reset();
+        sim
+    }
+}
+
+impl Sim {
+    /// Replaces the simulated circuit. State is reset only if the circuit changes.
+    pub fn init(&mut self, circuit: Circuit) -> state::Result {
+        if let Some(initial) = &self.initial {
+            state::validate(initial, circuit.q_num())?;
+        }
+        if self.circuit != circuit {
+            self.circuit = circuit;
+            self.reset();
+        }
+        Ok(())
+    }
+
+    /// Sets the state, which the register starts from, or `|0>` if `None`.
+    /// The state should be validated beforehand.
+    pub fn set_initial(&mut self, initial: Option<Vec<C>>) {
+        self.initial = initial;
+        self.reset();
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts measurement sampling from the given seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Sets noise channels, which are sampled from the seeded generator on every run.
+    pub fn set_noise(&mut self, noise: Vec<Noise>) {
+        self.noise = noise;
+    }
+
+    /// Number of threads, which operators are applied with.
+    pub fn threads(&self) -> usize {
+        self.pool
+            .as_ref()
+            .map_or(1, |pool| pool.current_num_threads())
+    }
+
+    /// Sets number of threads for applying operators. Single thread disables the thread pool at all.
+    pub fn set_threads(&mut self, threads: NonZeroUsize) -> Result<(), ThreadPoolBuildError> {
+        self.pool = match threads.get() {
+            1 => None,
+            threads => Some(Arc::new(
+                ThreadPoolBuilder::new().num_threads(threads).build()?,
+            )),
+        };
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        let len = 1_usize << self.circuit.q_num();
+        self.psi = match &self.initial {
+            Some(initial) if initial.len() == len => initial.clone(),
+            _ => {
+                let mut psi = vec![C::default(); len];
+                psi[0] = C::new(1.0, 0.0);
+                psi
+            }
+        };
+        self.psi.resize(len.max(MIN_BUFFER_LEN), C::default());
+        self.class = 0;
+    }
+
+    pub fn finish(&mut self) -> &mut Self {
+        let ops = std::mem::take(&mut self.circuit.ops);
+        for op in &ops {
+            self.apply_op(op);
+        }
+        self.circuit.ops = ops;
+        self
+    }
+
+    /// Runs the simulation as [`Sim::finish`] does, measuring time of every top-level instruction.
+    pub fn profile(&mut self) -> Profile {
+        let mut profile = Profile::default();
+        let ops = std::mem::take(&mut self.circuit.ops);
+        for op in &ops {
+            let start = Instant::now();
+            self.apply_op(op);
+            profile.add(op.name(), start.elapsed());
+        }
+        self.circuit.ops = ops;
+        profile.sorted()
+    }
+
+    /// Runs `shots` times, counting values of classical registers. If the circuit ends with measurements,
+    /// it is simulated once and outcomes are sampled from the state before them.
+    ///
+    /// The register is left in the state after the last shot.
+    pub fn sample(&mut self, shots: usize) -> Counts {
+        let ops = std::mem::take(&mut self.circuit.ops);
+        let terminal = match self.noise.is_empty() {
+            true => sample::terminal(&ops),
+            false => Err(Fallback::Noise),
+        };
+        let mut counts = Counts::new(self.circuit.c_num(), terminal.err());
+
+        match terminal {
+            Ok(start) => {
+                self.reset();
+                for op in &ops[..start] {
+                    self.apply_op(op);
+                }
+                let probs = self.get_probabilities();
+                let dist = WeightedIndex::new(&probs).ok();
+                let mut outcome = 0;
+                for _ in 0..shots {
+                    if let Some(dist) = &dist {
+                        outcome = dist.sample(&mut self.rng);
+                    }
+                    self.class = 0;
+                    for op in &ops[start..] {
+                        if let Op::Measure(q_mask, c_mask) = op {
+                            self.record(*q_mask, *c_mask, outcome);
+                        }
+                    }
+                    counts.add(self.class);
+                }
+                if shots > 0 {
+                    let q_mask = ops[start..].iter().fold(0, |acc, op| match op {
+                        Op::Measure(q_mask, _) => acc | q_mask,
+                        _ => acc,
+                    });
+                    self.project(&probs, q_mask, outcome & q_mask);
+                }
+            }
+            Err(_) => {
+                for _ in 0..shots {
+                    self.reset();
+                    for op in &ops {
+                        self.apply_op(op);
+                    }
+                    counts.add(self.class);
+                }
+            }
+        }
+        self.circuit.ops = ops;
+        counts
+    }
+
+    /// Samples as [`Sim::sample`] does, but restores the register afterwards, so the state is kept.
+    /// The generator is not restored, so the following runs continue the seeded sequence.
+    pub fn sample_aside(&mut self, shots: usize) -> Counts {
+        let (psi, class) = (self.psi.clone(), self.class);
+        let counts = self.sample(shots);
+        self.psi = psi;
+        self.class = class;
+        counts
+    }
+
+    /// Applies a single instruction, e.g. while stepping through the circuit.
+    pub fn step(&mut self, op: &Op) {
+        self.apply_op(op);
+    }
+
+    fn apply(&mut self, op: &MultiOp) {
+        let mut psi = vec![C::default(); self.psi.len()];
+        match &self.pool {
+            None => op.apply(&self.psi, &mut psi),
+            Some(pool) => pool.install(|| op.apply_sync(&self.psi, &mut psi)),
+        }
+        self.psi = psi;
+    }
+
+    fn apply_op(&mut self, op: &Op) {
+        match op {
+            Op::Macro(_, body) if !self.noise.is_empty() => {
+                for op in body {
+                    self.apply_op(op);
+                }
+            }
+            Op::Gate(_) | Op::Macro(..) | Op::Barrier(_) => {
+                if let Some(unitary) = op.unitary() {
+                    self.apply(&unitary);
+                }
+                if let Op::Gate(gate) = op {
+                    self.inject_noise(gate.act_on());
+                }
+            }
+            Op::Measure(q_mask, c_mask) => {
+                self.measure(*q_mask, *c_mask);
+                self.inject_noise(*q_mask);
+            }
+            Op::Reset(q_mask) => {
+                let outcome = self.collapse(*q_mask);
+                if outcome != 0 {
+                    self.apply(&op::x(outcome));
+                }
+                self.inject_noise(*q_mask);
+            }
+            Op::If(c_mask, val, op) => {
+                if circuit::gather(self.class, *c_mask) == *val {
+                    self.apply_op(op);
+                }
+            }
+        }
+    }
+
+    fn inject_noise(&mut self, act_on: usize) {
+        let len = 1 << self.circuit.q_num();
+        for noise in &self.noise {
+            noise.after_op(act_on, &mut self.psi[..len], &mut self.rng);
+        }
+    }
+
+    /// Samples the outcome of measurement of `q_mask` qubits and collapses the wavefunction.
+    fn collapse(&mut self, q_mask: usize) -> usize {
+        let probs = self.get_probabilities();
+        let outcome = match WeightedIndex::new(&probs) {
+            Ok(dist) => dist.sample(&mut self.rng) & q_mask,
+            Err(_) => return 0,
+        };
+        self.project(&probs, q_mask, outcome);
+        outcome
+    }
+
+    /// Projects the wavefunction onto `outcome` of `q_mask` qubits and normalises it.
+    fn project(&mut self, probs: &[f64], q_mask: usize, outcome: usize) {
+        let norm = probs
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| idx & q_mask == outcome)
+            .map(|(_, prob)| prob)
+            .sum::<f64>()
+            .sqrt();
+        for (idx, psi) in self.psi.iter_mut().enumerate() {
+            if idx & q_mask == outcome {
+                *psi /= norm;
+            } else {
+                *psi = C::default();
+            }
+        }
+    }
+
+    pub fn measure(&mut self, q_mask: usize, c_mask: usize) {
+        let outcome = self.collapse(q_mask);
+        self.record(q_mask, c_mask, outcome);
+    }
+
+    /// Writes outcome of measurement of `q_mask` qubits to `c_mask` bits, applying readout errors.
+    fn record(&mut self, q_mask: usize, c_mask: usize, outcome: usize) {
+        for (q, c) in circuit::bits(q_mask).zip(circuit::bits(c_mask)) {
+            let mut bit = (outcome >> q) & 1;
+            for noise in &self.noise {
+                if noise.channel == Channel::Readout
+                    && noise.q_mask & (1 << q) != 0
+                    && self.rng.gen_bool(noise.prob)
+                {
+                    bit ^= 1;
+                }
+            }
+            self.class &= !(1 << c);
+            self.class |= bit << c;
+        }
+    }
+
+    /// Wavefunction of the register without buffer padding.
+    pub fn state(&self) -> &[C] {
+        &self.psi[..1 << self.circuit.q_num()]
+    }
+
+    pub fn get_class(&self) -> usize {
+        self.class
+    }
+
+    pub fn get_polar_wavefunction(&self) -> Vec<(f64, f64)> {
+        self.state().iter().map(|z| z.to_polar()).collect()
+    }
+
+    pub fn get_probabilities(&self) -> Vec<f64> {
+        let state = self.state();
+        let abs = state.iter().map(C::norm_sqr).sum::<f64>();
+        state.iter().map(|z| z.norm_sqr() / abs).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qvnt::qasm::{Ast, Int, Sym};
+
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn bell_state() {
+        let mut sim = Sim::default();
+        sim.init(circuit(
+            "qreg q[2]; creg c[2]; h q[0]; cx q[0], q[1]; measure q -> c;",
+        ))
+        .unwrap();
+
+        for _ in 0..16 {
+            sim.reset();
+            sim.finish();
+            let class = sim.get_class();
+            assert!(class == 0b00 || class == 0b11);
+            assert_eq!(sim.get_probabilities()[class], 1.0);
+        }
+    }
+
+    /// Compares the simulation with [`qvnt::qasm::Sym`] on unitary parts of example circuits.
+    /// `Sym` applies `sdg` and `tdg` as `s` and `t`, so they are replaced to match it.
+    #[test]
+    fn matches_sym() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/examples");
+        let mut compared = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("qasm".as_ref()) {
+                continue;
+            }
+            let source: String = std::fs::read_to_string(&path)
+                .unwrap()
+                .lines()
+                .filter(|line| {
+                    let line = line.trim_start();
+                    !["measure", "reset", "if"]
+                        .iter()
+                        .any(|op| line.starts_with(op))
+                })
+                .map(|line| line.replace("sdg ", "s ").replace("tdg ", "t ") + "\n")
+                .collect();
+            let source: &'static str = Box::leak(source.into_boxed_str());
+            let ast = Ast::from_source(source).unwrap();
+            let (int, circuit) = match (Int::new(ast.clone()), Circuit::from_asts(&[ast])) {
+                (Ok(int), Ok(circuit)) => (int, circuit),
+                _ => continue,
+            };
+
+            let mut sym = Sym::new(int);
+            sym.finish();
+            let mut sim = Sim::default();
+            sim.init(circuit).unwrap();
+            sim.finish();
+            let (expected, actual) = (sym.get_probabilities(), sim.get_probabilities());
+            assert_eq!(expected.len(), actual.len(), "{}", path.display());
+            for (expected, actual) in expected.iter().zip(&actual) {
+                assert!((expected - actual).abs() < 1e-9, "{}", path.display());
+            }
+            compared += 1;
+        }
+        assert!(compared >= 10);
+
+        let mut sim = Sim::default();
+        sim.init(circuit("qreg q[1]; h q; s q; t q; tdg q; sdg q; h q;"))
+            .unwrap();
+        sim.finish();
+        assert!((sim.get_probabilities()[0] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn seeded() {
+        let run = |sim: &mut Sim| {
+            (0..32)
+                .map(|_| {
+                    sim.reset();
+                    sim.finish().get_class()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut sim = Sim::default();
+        sim.init(circuit("qreg q[4]; creg c[4]; h q; measure q -> c;"))
+            .unwrap();
+        sim.set_seed(42);
+        let outcomes = run(&mut sim);
+        sim.set_seed(42);
+        assert_eq!(run(&mut sim), outcomes);
+        assert_eq!(sim.seed(), 42);
+    }
+
+    #[test]
+    fn multi_thread() {
+        let circuit = circuit("qreg q[6]; h q; rz(pi/3) q[2]; cx q[0], q[5]; ry(1) q[4];");
+
+        let mut single = Sim::default();
+        single.init(circuit.clone()).unwrap();
+        let mut multi = Sim::default();
+        multi.set_threads(NonZeroUsize::new(4).unwrap()).unwrap();
+        multi.init(circuit).unwrap();
+
+        assert_eq!(single.threads(), 1);
+        assert_eq!(multi.threads(), 4);
+        assert_eq!(single.finish().state(), multi.finish().state());
+    }
+
+    #[test]
+    fn initial_state() {
+        let mut sim = Sim::default();
+        sim.init(circuit("qreg q[1]; h q;")).unwrap();
+
+        let sqrt = std::f64::consts::FRAC_1_SQRT_2;
+        sim.set_initial(Some(vec![C::new(sqrt, 0.0), C::new(-sqrt, 0.0)]));
+        sim.finish();
+        assert!((sim.get_probabilities()[1] - 1.0).abs() < 1e-12);
+
+        assert!(sim.init(circuit("qreg q[2];")).is_err());
+    }
+
+    #[test]
+    fn sample() {
+        let mut sim = Sim::default();
+        sim.init(circuit(
+            "qreg q[2]; creg c[2]; h q[0]; cx q[0], q[1]; measure q -> c;",
+        ))
+        .unwrap();
+        let counts: Vec<_> = sim.sample(1000).iter().collect();
+        assert_eq!(counts.len(), 2);
+        assert_eq!((counts[0].0, counts[1].0), (0b00, 0b11));
+        assert_eq!(counts[0].1 + counts[1].1, 1000);
+        assert!(counts[0].1 > 400 && counts[1].1 > 400);
+        assert_eq!(sim.get_probabilities()[sim.get_class()], 1.0);
+
+        sim.init(circuit(
+            "qreg q[1]; creg c[1]; h q; measure q -> c; if(c==1) x q; measure q -> c;",
+        ))
+        .unwrap();
+        let counts: Vec<_> = sim.sample(100).iter().collect();
+        assert_eq!(counts, [(0, 100)]);
+    }
+
+    #[test]
+    fn noisy() {
+        let circuit = circuit("qreg q[2]; creg c[2]; x q[0]; measure q -> c;");
+        let mut sim = Sim::default();
+        sim.init(circuit.clone()).unwrap();
+        let flip = |channel, target| {
+            crate::noise::Rule {
+                channel,
+                target: Some(target),
+                prob: 1.0,
+            }
+            .resolve(&circuit)
+            .unwrap()
+        };
+
+        sim.set_noise(vec![flip(Channel::BitFlip, "q[0]".to_string())]);
+        sim.reset();
+        assert_eq!(sim.finish().get_class(), 0b00);
+        // the qubit is flipped after the measurement as well
+        assert_eq!(sim.get_probabilities()[0b01], 1.0);
+
+        sim.set_noise(vec![flip(Channel::Readout, "q[1]".to_string())]);
+        sim.reset();
+        assert_eq!(sim.finish().get_class(), 0b11);
+    }
+
+    #[test]
+    fn sample_aside() {
+        let source = "qreg q[2]; creg c[2]; h q; measure q -> c;";
+        let (mut lhs, mut rhs) = (Sim::default(), Sim::default());
+        for sim in [&mut lhs, &mut rhs] {
+            sim.set_seed(7);
+            sim.init(circuit(source)).unwrap();
+            sim.finish();
+        }
+        let (state, class) = (lhs.state().to_vec(), lhs.get_class());
+
+        for _ in 0..2 {
+            assert_eq!(lhs.sample_aside(32), rhs.sample(32));
+        }
+        assert_eq!(lhs.state(), state.as_slice());
+        assert_eq!(lhs.get_class(), class);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    path::Path,
+};
+
+use num_complex::Complex64 as C;
+
+/// Allowed deviation of the squared norm of a loaded state from 1.
+pub const NORM_EPS: f64 = 1e-6;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Npy(String),
+    Csv(usize, String),
+    Length { expected: usize, found: usize },
+    Norm(f64),
+    TooManyQubits(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Npy(s) => write!(f, "Invalid .npy file: {s}"),
+            Error::Csv(line, s) => write!(f, "Invalid CSV at line {line}: {s:?}"),
+            Error::Length { expected, found } => write!(
+                f,
+                "State must have {expected} amplitudes to fit the register, but {found} are given"
+            ),
+            Error::Norm(norm) => write!(
+                f,
+                "State must be normalised, but its squared norm is {norm:.6}"
+            ),
+            Error::TooManyQubits(q_num) => write!(
+                f,
+                "State of {q_num} qubits has too many amplitudes to be addressed"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T = ()> = std::result::Result<T, Error>;
+
+/// Checks that `psi` is a normalised wavefunction of `q_num` qubits.
+pub fn validate(psi: &[C], q_num: usize) -> Result {
+    let expected = u32::try_from(q_num)
+        .ok()
+        .and_then(|q_num| 1_usize.checked_shl(q_num))
+        .ok_or(Error::TooManyQubits(q_num))?;
+    if psi.len() != expected {
+        return Err(Error::Length {
+            expected,
+            found: psi.len(),
+        });
+    }
+    let norm = psi.iter().map(C::norm_sqr).sum::<f64>();
+    if (norm - 1.0).abs() > NORM_EPS {
+        return Err(Error::Norm(norm));
+    }
+    Ok(())
+}
+
+fn is_csv(path: &Path) -> bool {
+    matches!(path.extension(), Some(ext) if ext.eq_ignore_ascii_case("csv"))
+}
+
+/// Serializes the wavefunction as 1-D `complex128` array in NumPy's .npy format.
+pub fn to_npy(psi: &[C]) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '<c16', 'fortran_order': False, 'shape': ({},), }}",
+        psi.len()
+    );
+    // magic, version, header length, header itself and trailing newline are aligned to 64 bytes
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header += &" ".repeat((64 - unpadded % 64) % 64);
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(NPY_MAGIC.len() + 4 + header.len() + 16 * psi.len());
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for z in psi {
+        bytes.extend_from_slice(&z.re.to_le_bytes());
+        bytes.extend_from_slice(&z.im.to_le_bytes());
+    }
+    bytes
+}
+
+/// Deserializes 1-D array of `complex128`, `complex64`, `float64` or `float32` from NumPy's .npy format.
+pub fn from_npy(bytes: &[u8]) -> Result<Vec<C>> {
+    let err = |s: &str| Error::Npy(s.to_string());
+
+    let rest = bytes
+        .strip_prefix(NPY_MAGIC)
+        .ok_or_else(|| err("no magic string"))?;
+    let (header_len, rest) = match rest {
+        [1, _, l0, l1, rest @ ..] => (u16::from_le_bytes([*l0, *l1]) as usize, rest),
+        [2 | 3, _, l0, l1, l2, l3, rest @ ..] => {
+            (u32::from_le_bytes([*l0, *l1, *l2, *l3]) as usize, rest)
+        }
+        _ => return Err(err("unsupported version")),
+    };
+    if rest.len() < header_len {
+        return Err(err("truncated header"));
+    }
+    let (header, data) = rest.split_at(header_len);
+    let header = std::str::from_utf8(header).map_err(|_| err("header is not UTF-8"))?;
+
+    let value_of = |key: &str| {
+        let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+        Some(header[start..].trim_start())
+    };
+
+    let descr = value_of("descr")
+        .and_then(|s| s.strip_prefix('\''))
+        .and_then(|s| s.split('\'').next())
+        .ok_or_else(|| err("no 'descr' key"))?;
+    let len = value_of("shape")
+        .and_then(|s| s.strip_prefix('('))
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| err("no 'shape' key"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .try_fold(1_usize, |len, dim| Some(len * dim.parse::<usize>().ok()?))
+        .ok_or_else(|| err("invalid 'shape'"))?;
+
+    let (size, read): (usize, fn(&[u8]) -> C) = match descr {
+        "<c16" => (16, |b| {
+            C::new(
+                f64::from_le_bytes(b[..8].try_into().unwrap()),
+                f64::from_le_bytes(b[8..].try_into().unwrap()),
+            )
+        }),
+        "<c8" => (8, |b| {
+            C::new(
+                f32::from_le_bytes(b[..4].try_into().unwrap()) as f64,
+                f32::from_le_bytes(b[4..].try_into().unwrap()) as f64,
+            )
+        }),
+        "<f8" => (8, |b| {
+            C::new(f64::from_le_bytes(b.try_into().unwrap()), 0.0)
+        }),
+        "<f4" => (4, |b| {
+            C::new(f32::from_le_bytes(b.try_into().unwrap()) as f64, 0.0)
+        }),
+        descr => return Err(Error::Npy(format!("unsupported dtype {descr:?}"))),
+    };
+    if data.len() != len * size {
+        return Err(err("data length does not match 'shape'"));
+    }
+
+    Ok(data.chunks_exact(size).map(read).collect())
+}
+
+/// Serializes the wavefunction as CSV lines `re,im`.
+pub fn to_csv(psi: &[C]) -> String {
+    psi.iter().map(|z| format!("{},{}\n", z.re, z.im)).collect()
+}
+
+/// Deserializes the wavefunction from CSV, where every line is `re` or `re,im`.
+/// Empty lines and lines started with `#` are skipped.
+pub fn from_csv(source: &str) -> Result<Vec<C>> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(idx, line)| {
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<std::result::Result<Vec<_>, _>>();
+            match values.as_deref() {
+                Ok([re]) => Ok(C::new(*re, 0.0)),
+                Ok([re, im]) => Ok(C::new(*re, *im)),
+                _ => Err(Error::Csv(idx, line.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// Serializes the wavefunction for file `path`: as CSV for `*.csv` files and as .npy otherwise.
+pub fn serialize(path: &Path, psi: &[C]) -> Vec<u8> {
+    if is_csv(path) {
+        to_csv(psi).into_bytes()
+    } else {
+        to_npy(psi)
+    }
+}
+
+/// Deserializes the wavefunction from content of file `path`: as CSV for `*.csv` files and as .npy otherwise.
+pub fn deserialize(path: &Path, bytes: &[u8]) -> Result<Vec<C>> {
+    if is_csv(path) {
+        from_csv(&String::from_utf8_lossy(bytes))
+    } else {
+        from_npy(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_roundtrip() {
+        let psi = vec![
+            C::new(0.5, 0.0),
+            C::new(0.0, -0.5),
+            C::new(0.5, 0.5),
+            C::default(),
+        ];
+        let bytes = to_npy(&psi);
+
+        assert_eq!(bytes.len() % 64, 0);
+        assert_eq!(from_npy(&bytes), Ok(psi.clone()));
+        assert_eq!(validate(&psi, 2), Ok(()));
+        assert_eq!(
+            validate(&psi, 3),
+            Err(Error::Length {
+                expected: 8,
+                found: 4
+            })
+        );
+        assert_eq!(validate(&psi, 64), Err(Error::TooManyQubits(64)));
+    }
+
+    #[test]
+    fn csv_parse() {
+        let psi = from_csv("# amplitudes\n0.6\n0,0.8\n\n").unwrap();
+        assert_eq!(psi, vec![C::new(0.6, 0.0), C::new(0.0, 0.8)]);
+        assert_eq!(from_csv(&to_csv(&psi)), Ok(psi));
+
+        assert_eq!(
+            from_csv("1\n1,2,3"),
+            Err(Error::Csv(2, "1,2,3".to_string()))
+        );
+        assert!(matches!(
+            validate(&[C::new(1.0, 0.0), C::new(1.0, 0.0)], 1),
+            Err(Error::Norm(_))
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+
+use crate::{circuit, params, watch::Watch};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnusedParameter(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnusedParameter(var) => write!(
+                f,
+                "Parameter {var:?} is not used in gate arguments of the current circuit"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Evenly spaced values of the parameter, including both bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub var: String,
+    pub from: f64,
+    pub to: f64,
+    pub steps: usize,
+}
+
+impl Range {
+    /// Parses `VAR FROM TO STEPS`, where bounds are expressions like `pi/2`.
+    pub fn parse(var: &str, from: &str, to: &str, steps: &str) -> Option<Self> {
+        Some(Self {
+            var: params::is_var(var).then(|| var.to_string())?,
+            from: circuit::eval(from, None).ok()?,
+            to: circuit::eval(to, None).ok()?,
+            steps: steps.parse().ok().filter(|&steps| steps > 0)?,
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.steps).map(move |step| match self.steps {
+            1 => self.from,
+            steps => self.from + (self.to - self.from) * step as f64 / (steps - 1) as f64,
+        })
+    }
+}
+
+/// Evaluation of the watch expression over the grid of one or two parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    pub ranges: Vec<Range>,
+    pub watch: Watch,
+}
+
+impl Sweep {
+    /// Calls `simulate` with every combination of parameter values and collects results.
+    pub fn run<E>(
+        &self,
+        mut simulate: impl FnMut(&[(String, f64)]) -> Result<f64, E>,
+    ) -> Result<Table, E> {
+        let mut points = vec![vec![]];
+        for range in &self.ranges {
+            points = points
+                .into_iter()
+                .flat_map(|point: Vec<f64>| {
+                    range.values().map(move |value| {
+                        let mut point = point.clone();
+                        point.push(value);
+                        point
+                    })
+                })
+                .collect();
+        }
+
+        let vars: Vec<_> = self.ranges.iter().map(|range| range.var.clone()).collect();
+        let mut rows = Vec::with_capacity(points.len());
+        for point in points {
+            let bound: Vec<_> = vars.iter().cloned().zip(point.iter().cloned()).collect();
+            let value = simulate(&bound)?;
+            rows.push((point, value));
+        }
+        Ok(Table {
+            vars,
+            quantity: self.watch.to_string(),
+            rows,
+        })
+    }
+}
+
+/// Results of the sweep: values of parameters and the quantity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    vars: Vec<String>,
+    quantity: String,
+    rows: Vec<(Vec<f64>, f64)>,
+}
+
+impl Table {
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.vars.join(",");
+        match self.quantity.contains([',', '"']) {
+            true => csv += &format!(",\"{}\"\n", self.quantity.replace('"', "\"\"")),
+            false => csv += &format!(",{}\n", self.quantity),
+        }
+        for (point, value) in &self.rows {
+            for param in point {
+                csv += &format!("{param},");
+            }
+            csv += &format!("{value}\n");
+        }
+        csv
+    }
+}
+
+impl fmt::Display for Table {
+    /// One-parameter sweep is shown as a list, two-parameter one as a grid
+    /// with values of the first parameter in rows and of the second one in columns.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.vars.as_slice() {
+            [var0, var1] => {
+                let cols = self
+                    .rows
+                    .iter()
+                    .take_while(|(point, _)| point[0] == self.rows[0].0[0])
+                    .count();
+                let corner = format!("{var0} \\ {var1}");
+                let width = corner.len().max(8);
+                writeln!(f, "{}", self.quantity)?;
+                write!(f, "{corner:>width$}")?;
+                for (point, _) in &self.rows[..cols] {
+                    write!(f, "  {:>8.4}", point[1])?;
+                }
+                writeln!(f)?;
+                for row in self.rows.chunks(cols) {
+                    write!(f, "{:>width$.4}", row[0].0[0])?;
+                    for (_, value) in row {
+                        write!(f, "  {value:>8.4}")?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+            vars => {
+                let widths: Vec<_> = vars.iter().map(|var| var.len().max(8)).collect();
+                for (var, width) in vars.iter().zip(&widths) {
+                    write!(f, "{var:>width$}  ")?;
+                }
+                writeln!(f, "{}", self.quantity)?;
+                for (point, value) in &self.rows {
+                    for (param, width) in point.iter().zip(&widths) {
+                        write!(f, "{param:>width$.4}  ")?;
+                    }
+                    writeln!(f, "{value:>9.4}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid() {
+        let sweep = Sweep {
+            ranges: vec![
+                Range::parse("theta", "0", "pi", "3").unwrap(),
+                Range::parse("phi", "-1", "1", "2").unwrap(),
+            ],
+            watch: "prob q[0]".parse().unwrap(),
+        };
+        let table = sweep
+            .run(|vars| -> Result<f64, ()> { Ok(vars[0].1 * vars[1].1) })
+            .unwrap();
+
+        let pi = std::f64::consts::PI;
+        assert_eq!(
+            table.rows,
+            vec![
+                (vec![0.0, -1.0], -0.0),
+                (vec![0.0, 1.0], 0.0),
+                (vec![pi / 2.0, -1.0], -pi / 2.0),
+                (vec![pi / 2.0, 1.0], pi / 2.0),
+                (vec![pi, -1.0], -pi),
+                (vec![pi, 1.0], pi),
+            ]
+        );
+        assert_eq!(
+            table.to_csv().lines().take(2).collect::<Vec<_>>(),
+            ["theta,phi,prob q[0]", "0,-1,-0"]
+        );
+        assert_eq!(Range::parse("2x", "0", "1", "2"), None);
+        assert_eq!(Range::parse("x", "0", "1", "0"), None);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use qvnt::prelude::Int;
+
+use crate::{
+    cli::CliArgs,
+    int_tree::Tree,
+    process,
+    program::{self, ProgramError, ProgramResult, ROOT_TAG},
+};
+
+/// Comment prefix of expectations, embedded into QASM source.
+const EXPECT_PREFIX: &str = "// @expect";
+/// Extension of companion files with expectations, one per line.
+const EXPECT_EXTENSION: &str = "expect";
+/// Seed of every test file, if it's not specified in command line, so results are reproducible.
+const DEFAULT_SEED: u64 = 0;
+
+/// Circuit under test with its expectations, which are assertions of the `:assert` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFile {
+    pub path: PathBuf,
+    pub expectations: Vec<String>,
+}
+
+impl TestFile {
+    /// Reads expectations of the QASM file, returns `None` if there are no ones.
+    pub fn read(path: &Path) -> std::io::Result<Option<Self>> {
+        let source = std::fs::read_to_string(path)?;
+        let mut expectations: Vec<_> = source
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix(EXPECT_PREFIX))
+            .map(|expectation| expectation.trim().to_string())
+            .collect();
+        let companion = path.with_extension(EXPECT_EXTENSION);
+        if companion.is_file() {
+            expectations.extend(
+                std::fs::read_to_string(companion)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with(['#', '/']))
+                    .map(str::to_string),
+            );
+        }
+        Ok((!expectations.is_empty()).then(|| Self {
+            path: path.to_path_buf(),
+            expectations,
+        }))
+    }
+}
+
+/// Finds QASM files with expectations in `dir` and its subdirectories, or checks `dir` is such a file.
+pub fn discover(dir: &Path) -> std::io::Result<Vec<TestFile>> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(&path, files)?;
+            } else if path.extension().is_some_and(|ext| ext == "qasm") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    match dir.is_dir() {
+        true => walk(dir, &mut files)?,
+        false => files.push(dir.to_path_buf()),
+    }
+    files.sort();
+
+    let mut tests = vec![];
+    for path in files {
+        tests.extend(TestFile::read(&path)?);
+    }
+    Ok(tests)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// Expectation doesn't hold.
+    Failed(String),
+    /// Circuit or expectation cannot be processed.
+    Error(String),
+}
+
+/// Result of a single expectation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub file: String,
+    pub expectation: String,
+    pub outcome: Outcome,
+    pub time: Duration,
+}
+
+/// Loads the file into a fresh interpreter, runs it once and checks expectations one by one.
+pub fn run_file(cli: &CliArgs, test: &TestFile) -> ProgramResult<Vec<Case>> {
+    let mut process = program::new_process(cli)?;
+    process.set_seed(cli.seed.unwrap_or(DEFAULT_SEED));
+    let mut int_tree = Tree::<Int>::with_root(ROOT_TAG);
+
+    let start = Instant::now();
+    let run = process
+        .load_qasm(&mut int_tree, test.path.clone(), true)
+        .and_then(|_| process.process(&mut int_tree, ":go".to_string()));
+    let run_time = start.elapsed();
+
+    let file = test.path.display().to_string();
+    let mut cases = vec![];
+    for expectation in &test.expectations {
+        let start = Instant::now();
+        let outcome = match &run {
+            Err(err) => Outcome::Error(err.to_string()),
+            Ok(_) => match process.process(&mut int_tree, format!(":assert {expectation}")) {
+                Ok(_) => Outcome::Passed,
+                Err(process::Error::Assertion(failure)) => Outcome::Failed(failure.to_string()),
+                Err(err) => Outcome::Error(err.to_string()),
+            },
+        };
+        cases.push(Case {
+            file: file.clone(),
+            expectation: expectation.clone(),
+            outcome,
+            time: run_time + start.elapsed(),
+        });
+    }
+    Ok(cases)
+}
+
+/// Results of all tests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub cases: Vec<Case>,
+}
+
+impl Report {
+    pub fn failed(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| case.outcome != Outcome::Passed)
+            .count()
+    }
+
+    pub fn to_junit(&self) -> String {
+        let count = |error: bool| {
+            self.cases
+                .iter()
+                .filter(|case| match case.outcome {
+                    Outcome::Passed => false,
+                    Outcome::Failed(_) => !error,
+                    Outcome::Error(_) => error,
+                })
+                .count()
+        };
+        let time: Duration = self.cases.iter().map(|case| case.time).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml += &format!(
+            "<testsuite name=\"qvnt-i\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            self.cases.len(),
+            count(false),
+            count(true),
+            time.as_secs_f64()
+        );
+        for case in &self.cases {
+            xml += &format!(
+                "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(&case.file),
+                xml_escape(&case.expectation),
+                case.time.as_secs_f64()
+            );
+            match &case.outcome {
+                Outcome::Passed => xml += "/>\n",
+                Outcome::Failed(message) => {
+                    xml += &format!(
+                        ">\n    <failure message=\"{}\"/>\n  </testcase>\n",
+                        xml_escape(message)
+                    )
+                }
+                Outcome::Error(message) => {
+                    xml += &format!(
+                        ">\n    <error message=\"{}\"/>\n  </testcase>\n",
+                        xml_escape(message)
+                    )
+                }
+            }
+        }
+        xml + "</testsuite>\n"
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut files: Vec<&str> = self.cases.iter().map(|case| case.file.as_str()).collect();
+        files.dedup();
+        for file in files {
+            let cases: Vec<_> = self.cases.iter().filter(|case| case.file == file).collect();
+            match cases.iter().all(|case| case.outcome == Outcome::Passed) {
+                true => writeln!(f, "PASS {file} ({} expectations)", cases.len())?,
+                false => writeln!(f, "FAIL {file}")?,
+            }
+            for case in cases {
+                match &case.outcome {
+                    Outcome::Passed => {}
+                    Outcome::Failed(message) => writeln!(f, "  {message}")?,
+                    Outcome::Error(message) => {
+                        writeln!(f, "  Error in `{}`: {message}", case.expectation)?
+                    }
+                }
+            }
+        }
+        let failed = self.failed();
+        write!(
+            f,
+            "{} expectations: {} passed, {} failed",
+            self.cases.len(),
+            self.cases.len() - failed,
+            failed
+        )
+    }
+}
+
+/// Runs the `test` subcommand.
+pub fn run(cli: &CliArgs, dir: &Path, junit: Option<&Path>) -> ProgramResult {
+    let tests = discover(dir).map_err(process::Error::Io)?;
+    let mut report = Report::default();
+    for test in &tests {
+        report.cases.extend(run_file(cli, test)?);
+    }
+
+    println!("{}", report);
+    if let Some(junit) = junit {
+        std::fs::write(junit, report.to_junit()).map_err(process::Error::Io)?;
+    }
+    match report.failed() {
+        0 => Ok(()),
+        failed => Err(ProgramError::FailedTests(failed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expectations() {
+        let dir = std::env::temp_dir().join(format!("qvnt-i-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(
+            dir.join("bell.qasm"),
+            "OPENQASM 2.0;\nqreg q[2]; creg c[2];\nh q[0]; cx q[0], q[1];\n\
+            // @expect prob q=00 0.5 ±0.01\n// @expect prob q=01 0.5\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("nested/flip.qasm"),
+            "OPENQASM 2.0;\nqreg q[1]; creg c[1];\nx q; measure q -> c;\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("nested/flip.expect"), "# result\nclass c == 1\n").unwrap();
+        std::fs::write(dir.join("plain.qasm"), "OPENQASM 2.0;\nqreg q[1];\n").unwrap();
+
+        let tests = discover(&dir).unwrap();
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[1].expectations, ["class c == 1"]);
+
+        let cli = <CliArgs as clap::Parser>::parse_from(["qvnt-i", "--threads", "1"]);
+        let mut report = Report::default();
+        for test in &tests {
+            report.cases.extend(run_file(&cli, test).unwrap());
+        }
+        let outcomes: Vec<_> = report.cases.iter().map(|case| &case.outcome).collect();
+        assert_eq!(outcomes[0], &Outcome::Passed);
+        assert!(matches!(outcomes[1], Outcome::Failed(_)));
+        assert_eq!(outcomes[2], &Outcome::Passed);
+        assert_eq!(report.failed(), 1);
+        assert!(report
+            .to_junit()
+            .contains("<testsuite name=\"qvnt-i\" tests=\"3\" failures=\"1\" errors=\"0\""));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
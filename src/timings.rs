@@ -0,0 +1,109 @@
+use std::{fmt, time::Duration};
+
+/// Wall time spent by the stages of processing of a single line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Parsing QASM source into `Ast`.
+    pub parse: Duration,
+    /// Building `Int` from `Ast`s.
+    pub build: Duration,
+    /// Lowering the program and initialising the simulator.
+    pub init: Duration,
+    /// Running the simulation.
+    pub finish: Duration,
+    pub total: Duration,
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Time: parse {:.3?}, build {:.3?}, init {:.3?}, finish {:.3?}, total {:.3?}",
+            self.parse, self.build, self.init, self.finish, self.total
+        )
+    }
+}
+
+/// Simulation time of every kind of instruction: name, count and total duration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile(pub Vec<(String, usize, Duration)>);
+
+impl Profile {
+    pub fn add(&mut self, name: String, elapsed: Duration) {
+        match self.0.iter_mut().find(|(entry, ..)| *entry == name) {
+            Some((_, count, time)) => {
+                *count += 1;
+                *time += elapsed;
+            }
+            None => self.0.push((name, 1, elapsed)),
+        }
+    }
+
+    /// Sorts entries from the slowest to the fastest.
+    pub fn sorted(mut self) -> Self {
+        self.0.sort_by_key(|(_, _, time)| std::cmp::Reverse(*time));
+        self
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: Duration = self.0.iter().map(|(_, _, time)| *time).sum();
+        let width = self
+            .0
+            .iter()
+            .map(|(name, ..)| name.len())
+            .chain([2])
+            .max()
+            .unwrap_or(0);
+
+        writeln!(
+            f,
+            "{:<width$}  {:>8}  {:>12}  {:>7}",
+            "Op", "Count", "Time", "Share"
+        )?;
+        for (name, count, time) in &self.0 {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                100.0 * time.as_secs_f64() / total.as_secs_f64()
+            };
+            writeln!(
+                f,
+                "{:<width$}  {:>8}  {:>12}  {:>6.1}%",
+                name,
+                count,
+                format!("{time:.3?}"),
+                share
+            )?;
+        }
+        write!(f, "Total: {total:.3?}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile() {
+        let mut profile = Profile::default();
+        profile.add("h".to_string(), Duration::from_millis(1));
+        profile.add("foo".to_string(), Duration::from_millis(3));
+        profile.add("h".to_string(), Duration::from_millis(1));
+        let profile = profile.sorted();
+
+        assert_eq!(
+            profile.0,
+            vec![
+                ("foo".to_string(), 1, Duration::from_millis(3)),
+                ("h".to_string(), 2, Duration::from_millis(2)),
+            ]
+        );
+        let table = profile.to_string();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("foo") && lines[1].ends_with("60.0%"));
+        assert_eq!(lines[3], "Total: 5.000ms");
+    }
+}
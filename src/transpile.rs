@@ -0,0 +1,573 @@
+use std::{
+    f64::consts::{FRAC_PI_2, FRAC_PI_4, PI},
+    fmt,
+};
+
+use num_complex::Complex64 as C;
+
+use crate::circuit::{self, gates, Circuit, Gate, Macro, Op};
+
+/// Target gate set, which is used if none is given.
+pub const DEFAULT_BASIS: &str = "cx,rz,sx";
+
+/// Gates, which can make up a basis.
+const BASIS_GATES: &[&str] = &["cx", "cz", "u3", "rz", "sx", "ry", "rx"];
+
+const EPS: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    NoEntangler(String),
+    NoSingleQubit(String),
+    UnknownGate(String),
+    UnsupportedGate(String),
+    Decomposition(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoEntangler(basis) => write!(
+                f,
+                "Basis {basis:?} has no two-qubit gate: `cx` or `cz` is required"
+            ),
+            Error::NoSingleQubit(basis) => write!(
+                f,
+                "Basis {basis:?} cannot express single-qubit gates: `u3`, or `rz` with one of `sx`, `ry`, `rx` is required"
+            ),
+            Error::UnknownGate(name) => write!(f, "Gate {name:?} cannot be decomposed"),
+            Error::UnsupportedGate(name) => write!(
+                f,
+                "Gate {name:?} cannot be a basis gate, expected some of `{}`",
+                BASIS_GATES.join(",")
+            ),
+            Error::Decomposition(name) => {
+                write!(f, "Decomposition of gate {name:?} does not match it")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T = ()> = std::result::Result<T, Error>;
+
+/// Matrix of single-qubit operator.
+type Matrix = [[C; 2]; 2];
+
+const X: Matrix = [
+    [C::new(0.0, 0.0), C::new(1.0, 0.0)],
+    [C::new(1.0, 0.0), C::new(0.0, 0.0)],
+];
+
+fn mul(lhs: &Matrix, rhs: &Matrix) -> Matrix {
+    let mut res = [[C::default(); 2]; 2];
+    for (i, row) in res.iter_mut().enumerate() {
+        for (j, elem) in row.iter_mut().enumerate() {
+            *elem = lhs[i][0] * rhs[0][j] + lhs[i][1] * rhs[1][j];
+        }
+    }
+    res
+}
+
+fn dagger(u: &Matrix) -> Matrix {
+    [
+        [u[0][0].conj(), u[1][0].conj()],
+        [u[0][1].conj(), u[1][1].conj()],
+    ]
+}
+
+fn rz(angle: f64) -> Matrix {
+    [
+        [C::from_polar(1.0, -angle / 2.0), C::default()],
+        [C::default(), C::from_polar(1.0, angle / 2.0)],
+    ]
+}
+
+fn ry(angle: f64) -> Matrix {
+    let (sin, cos) = (angle / 2.0).sin_cos();
+    [
+        [C::new(cos, 0.0), C::new(-sin, 0.0)],
+        [C::new(sin, 0.0), C::new(cos, 0.0)],
+    ]
+}
+
+fn phase(angle: f64) -> Matrix {
+    [
+        [C::new(1.0, 0.0), C::default()],
+        [C::default(), C::from_polar(1.0, angle)],
+    ]
+}
+
+fn is_close(lhs: &Matrix, rhs: &Matrix) -> bool {
+    (0..4).all(|idx| (lhs[idx / 2][idx % 2] - rhs[idx / 2][idx % 2]).norm() < EPS)
+}
+
+/// Matrix of built-in single-qubit gate, as `qvnt` applies it.
+fn matrix_of(name: &str, args: &[f64]) -> Option<Matrix> {
+    let op = gates::process(name, &[1], args)?;
+    let col0 = circuit::apply(&op, &[C::new(1.0, 0.0), C::default()]);
+    let col1 = circuit::apply(&op, &[C::default(), C::new(1.0, 0.0)]);
+    Some([[col0[0], col1[0]], [col0[1], col1[1]]])
+}
+
+/// Decomposes `u` as `exp(i*alpha) Rz(beta) Ry(gamma) Rz(delta)` and returns `(alpha, beta, gamma, delta)`.
+fn zyz(u: &Matrix) -> (f64, f64, f64, f64) {
+    let (cos, sin) = (u[0][0].norm(), u[1][0].norm());
+    let gamma = 2.0 * sin.atan2(cos);
+    let (beta, delta) = if sin < EPS {
+        ((u[1][1] / u[0][0]).arg(), 0.0)
+    } else if cos < EPS {
+        ((u[1][0] / -u[0][1]).arg(), 0.0)
+    } else {
+        ((u[1][0] / u[0][0]).arg(), (u[1][1] / u[1][0]).arg())
+    };
+
+    let m = mul(&rz(beta), &mul(&ry(gamma), &rz(delta)));
+    let (i, j) = if m[0][0].norm() >= m[1][0].norm() {
+        (0, 0)
+    } else {
+        (1, 0)
+    };
+    let alpha = (u[i][j] / m[i][j]).arg();
+    (alpha, beta, gamma, delta)
+}
+
+/// Principal square root of unitary matrix.
+fn sqrt(u: &Matrix) -> Matrix {
+    let det = u[0][0] * u[1][1] - u[0][1] * u[1][0];
+    let trace = u[0][0] + u[1][1];
+    let s = det.sqrt();
+    let s = if (trace + 2.0 * s).norm() >= (trace - 2.0 * s).norm() {
+        s
+    } else {
+        -s
+    };
+    let t = (trace + 2.0 * s).sqrt();
+    [
+        [(u[0][0] + s) / t, u[0][1] / t],
+        [u[1][0] / t, (u[1][1] + s) / t],
+    ]
+}
+
+/// Wraps angle into `(-pi, pi]`.
+fn normalize(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(2.0 * PI);
+    if angle > PI {
+        angle - 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+/// Single-qubit operator `u` on qubit `target`, controlled by all qubits of `ctrls` mask.
+#[derive(Debug, Clone, Copy)]
+struct Prim {
+    u: Matrix,
+    target: usize,
+    ctrls: usize,
+}
+
+impl Prim {
+    fn new(u: Matrix, target: usize) -> Self {
+        Self {
+            u,
+            target,
+            ctrls: 0,
+        }
+    }
+
+    fn cx(ctrl: usize, target: usize) -> Self {
+        Self {
+            u: X,
+            target,
+            ctrls: 1 << ctrl,
+        }
+    }
+
+    fn apply(&self, psi: &mut [C]) {
+        let t_mask = 1 << self.target;
+        for idx in 0..psi.len() {
+            if idx & t_mask == 0 && idx & self.ctrls == self.ctrls {
+                let (a, b) = (psi[idx], psi[idx | t_mask]);
+                psi[idx] = self.u[0][0] * a + self.u[0][1] * b;
+                psi[idx | t_mask] = self.u[1][0] * a + self.u[1][1] * b;
+            }
+        }
+    }
+}
+
+/// Decomposition of built-in two-qubit gate, acting on qubits 0 and 1, up to global phase.
+fn two_qubit(name: &str, args: &[f64]) -> Option<Vec<Prim>> {
+    let h = matrix_of("h", &[])?;
+    let rzz = |angle: f64| vec![Prim::cx(0, 1), Prim::new(rz(angle), 1), Prim::cx(0, 1)];
+    let rxx = |angle: f64| {
+        let mut prims = vec![Prim::new(h, 0), Prim::new(h, 1)];
+        prims.extend(rzz(angle));
+        prims.extend([Prim::new(h, 0), Prim::new(h, 1)]);
+        prims
+    };
+    let ryy = |angle: f64| {
+        let (rx, rx_dgr) = (
+            matrix_of("rx", &[FRAC_PI_2])?,
+            matrix_of("rx", &[-FRAC_PI_2])?,
+        );
+        let mut prims = vec![Prim::new(rx, 0), Prim::new(rx, 1)];
+        prims.extend(rzz(angle));
+        prims.extend([Prim::new(rx_dgr, 0), Prim::new(rx_dgr, 1)]);
+        Some(prims)
+    };
+
+    Some(match (name, args) {
+        ("swap", []) => vec![Prim::cx(0, 1), Prim::cx(1, 0), Prim::cx(0, 1)],
+        ("rzz", &[angle]) => rzz(angle),
+        ("rxx", &[angle]) => rxx(angle),
+        ("ryy", &[angle]) => ryy(angle)?,
+        ("i_swap", []) => [rxx(-FRAC_PI_2), ryy(-FRAC_PI_2)?].concat(),
+        ("sqrt_i_swap", []) => [rxx(-FRAC_PI_4), ryy(-FRAC_PI_4)?].concat(),
+        ("sqrt_swap", []) => [rxx(FRAC_PI_4), ryy(FRAC_PI_4)?, rzz(FRAC_PI_4)].concat(),
+        _ => return None,
+    })
+}
+
+/// Global phase, which `prims` differ from the built-in gate by.
+fn phase_of(name: &str, args: &[f64], prims: &[Prim]) -> Option<f64> {
+    let op = gates::process(name, &[0b01, 0b10], args)?;
+    let mut overlap = C::default();
+    for idx in 0..4 {
+        let mut psi = vec![C::default(); 4];
+        psi[idx] = C::new(1.0, 0.0);
+        let expected = circuit::apply(&op, &psi);
+        prims.iter().for_each(|prim| prim.apply(&mut psi));
+        overlap += psi
+            .iter()
+            .zip(&expected)
+            .map(|(found, expected)| found.conj() * expected)
+            .sum::<C>();
+    }
+    ((overlap.norm() - 4.0).abs() < 1e-6).then(|| overlap.arg())
+}
+
+/// Splits built-in gate into controlled single-qubit operators.
+fn prims(gate: &Gate) -> Result<Vec<Prim>> {
+    let (ctrl, base) = gate.controls();
+    let base = base.to_lowercase();
+    let ctrls = gate.regs[..ctrl].iter().fold(0, |acc, reg| acc | reg);
+    let targets: Vec<usize> =
+        circuit::bits(gate.regs[ctrl..].iter().fold(0, |acc, reg| acc | reg)).collect();
+    let unknown = || Error::UnknownGate(gate.name.clone());
+    let controlled = |prim: Prim| Prim {
+        ctrls: prim.ctrls | ctrls,
+        ..prim
+    };
+
+    let prims = if base == "qft" {
+        // the same sequence, as `qvnt` builds
+        let h = matrix_of("h", &[]).ok_or_else(unknown)?;
+        let mut prims = vec![];
+        for (i, &target) in targets.iter().enumerate() {
+            prims.push(controlled(Prim::new(h, target)));
+            for (j, &next) in targets.iter().enumerate().skip(i + 1) {
+                prims.push(Prim {
+                    u: rz(PI * 0.5_f64.powi((j - i) as i32)),
+                    target: next,
+                    ctrls: ctrls | 1 << target,
+                });
+            }
+        }
+        prims
+    } else if let Some(u) = matrix_of(&base, &gate.args) {
+        targets
+            .iter()
+            .map(|&target| controlled(Prim::new(u, target)))
+            .collect()
+    } else {
+        let local = two_qubit(&base, &gate.args).ok_or_else(unknown)?;
+        let alpha = phase_of(&base, &gate.args, &local)
+            .ok_or_else(|| Error::Decomposition(gate.name.clone()))?;
+        if targets.len() != 2 {
+            return Err(unknown());
+        }
+        let mut prims: Vec<Prim> = local
+            .into_iter()
+            .map(|prim| Prim {
+                u: prim.u,
+                target: targets[prim.target],
+                ctrls: circuit::bits(prim.ctrls).fold(0, |acc, bit| acc | 1 << targets[bit]),
+            })
+            .map(controlled)
+            .collect();
+        if ctrls != 0 && alpha.abs() > EPS {
+            let target = ctrls.trailing_zeros() as usize;
+            prims.push(Prim {
+                u: phase(alpha),
+                target,
+                ctrls: ctrls & !(1 << target),
+            });
+        }
+        prims
+    };
+    Ok(prims)
+}
+
+/// Target gate set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Basis(Vec<String>);
+
+impl std::str::FromStr for Basis {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| match BASIS_GATES.contains(&name.as_str()) {
+                true => Ok(name),
+                false => Err(Error::UnsupportedGate(name)),
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for Basis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
+
+impl Basis {
+    fn has(&self, name: &str) -> bool {
+        self.0.iter().any(|gate| gate == name)
+    }
+
+    fn gate(name: &str, regs: Vec<usize>, args: Vec<f64>) -> Op {
+        Op::Gate(Gate {
+            name: name.to_string(),
+            regs,
+            args,
+        })
+    }
+
+    fn rz(angle: f64, target: usize, ops: &mut Vec<Op>) {
+        let angle = normalize(angle);
+        if angle.abs() > EPS {
+            ops.push(Self::gate("rz", vec![1 << target], vec![angle]));
+        }
+    }
+
+    fn sx(target: usize) -> Op {
+        let gate = |name: &str| Self::gate(name, vec![1 << target], vec![]);
+        Op::Macro(
+            Gate {
+                name: "sx".to_string(),
+                regs: vec![1 << target],
+                args: vec![],
+            },
+            vec![gate("h"), gate("s"), gate("h")],
+        )
+    }
+
+    fn single(&self, u: &Matrix, target: usize, ops: &mut Vec<Op>) -> Result {
+        let (_, beta, gamma, delta) = zyz(u);
+        let rotation = |name: &str, angle: f64| Self::gate(name, vec![1 << target], vec![angle]);
+
+        if gamma.abs() < EPS && normalize(beta + delta).abs() < EPS {
+            return Ok(());
+        }
+        if self.has("u3") {
+            ops.push(Self::gate(
+                "u3",
+                vec![1 << target],
+                vec![gamma, normalize(beta), normalize(delta)],
+            ));
+        } else if self.has("rz") && gamma.abs() < EPS {
+            Self::rz(beta + delta, target, ops);
+        } else if self.has("rz") && self.has("sx") && (gamma - FRAC_PI_2).abs() < EPS {
+            Self::rz(delta - FRAC_PI_2, target, ops);
+            ops.push(Self::sx(target));
+            Self::rz(beta + FRAC_PI_2, target, ops);
+        } else if self.has("rz") && self.has("sx") {
+            Self::rz(delta, target, ops);
+            ops.push(Self::sx(target));
+            Self::rz(gamma + PI, target, ops);
+            ops.push(Self::sx(target));
+            Self::rz(beta + PI, target, ops);
+        } else if self.has("rz") && self.has("ry") {
+            Self::rz(delta, target, ops);
+            ops.push(rotation("ry", gamma));
+            Self::rz(beta, target, ops);
+        } else if self.has("rz") && self.has("rx") {
+            Self::rz(delta - FRAC_PI_2, target, ops);
+            ops.push(rotation("rx", gamma));
+            Self::rz(beta + FRAC_PI_2, target, ops);
+        } else {
+            return Err(Error::NoSingleQubit(self.to_string()));
+        }
+        Ok(())
+    }
+
+    fn cx(&self, ctrl: usize, target: usize, ops: &mut Vec<Op>) -> Result {
+        if self.has("cx") {
+            ops.push(Self::gate("cx", vec![1 << ctrl, 1 << target], vec![]));
+        } else if self.has("cz") {
+            let h = matrix_of("h", &[]).ok_or_else(|| Error::UnknownGate("h".to_string()))?;
+            self.single(&h, target, ops)?;
+            ops.push(Self::gate("cz", vec![1 << ctrl, 1 << target], vec![]));
+            self.single(&h, target, ops)?;
+        } else {
+            return Err(Error::NoEntangler(self.to_string()));
+        }
+        Ok(())
+    }
+
+    fn lower(&self, prim: Prim, ops: &mut Vec<Op>) -> Result {
+        let Prim { u, target, ctrls } = prim;
+        match ctrls.count_ones() {
+            0 => self.single(&u, target, ops),
+            1 if is_close(&u, &X) => self.cx(ctrls.trailing_zeros() as usize, target, ops),
+            1 => {
+                // u = exp(i*alpha) A X B X C, where A B C = I
+                let ctrl = ctrls.trailing_zeros() as usize;
+                let (alpha, beta, gamma, delta) = zyz(&u);
+                let a = mul(&rz(beta), &ry(gamma / 2.0));
+                let b = mul(&ry(-gamma / 2.0), &rz(-(delta + beta) / 2.0));
+                let c = rz((delta - beta) / 2.0);
+
+                self.single(&c, target, ops)?;
+                self.cx(ctrl, target, ops)?;
+                self.single(&b, target, ops)?;
+                self.cx(ctrl, target, ops)?;
+                self.single(&a, target, ops)?;
+                self.single(&phase(alpha), ctrl, ops)
+            }
+            _ => {
+                // multi-controlled gate is built from singly-controlled `v`, such that `v * v = u`
+                let last = usize::BITS as usize - 1 - ctrls.leading_zeros() as usize;
+                let rest = ctrls & !(1 << last);
+                let v = sqrt(&u);
+                let v_dgr = dagger(&v);
+                let prim = |u, target, ctrls| Prim { u, target, ctrls };
+
+                self.lower(prim(v, target, 1 << last), ops)?;
+                self.lower(prim(X, last, rest), ops)?;
+                self.lower(prim(v_dgr, target, 1 << last), ops)?;
+                self.lower(prim(X, last, rest), ops)?;
+                self.lower(prim(v, target, rest), ops)
+            }
+        }
+    }
+
+    fn lower_op(&self, op: &Op, ops: &mut Vec<Op>) -> Result {
+        match op {
+            Op::Gate(gate) if self.has(&gate.name.to_lowercase()) => ops.push(op.clone()),
+            Op::Gate(gate) => {
+                for prim in prims(gate)? {
+                    self.lower(prim, ops)?;
+                }
+            }
+            Op::If(c_mask, val, op) => {
+                let mut body = vec![];
+                self.lower_op(op, &mut body)?;
+                ops.extend(
+                    body.into_iter()
+                        .map(|op| Op::If(*c_mask, *val, Box::new(op))),
+                );
+            }
+            op => ops.push(op.clone()),
+        }
+        Ok(())
+    }
+
+    /// Decomposes every gate and macro of the circuit into gates of the basis.
+    ///
+    /// Gates, which are already in the basis, are kept as is.
+    /// `sx` gate is defined in the result as a macro, since it's not a built-in one.
+    pub fn transpile(&self, circuit: &Circuit) -> Result<Circuit> {
+        let mut transpiled = circuit.flatten();
+        let mut ops = Vec::with_capacity(transpiled.ops.len());
+        for op in &transpiled.ops {
+            self.lower_op(op, &mut ops)?;
+        }
+        transpiled.ops = ops;
+
+        if self.has("sx") {
+            transpiled.macros.insert(
+                "sx".to_string(),
+                Macro {
+                    regs: vec!["a".to_string()],
+                    args: vec![],
+                    body: ["h", "s", "h"]
+                        .iter()
+                        .map(|name| (name.to_string(), vec!["a".to_string()], vec![]))
+                        .collect(),
+                },
+            );
+        }
+        Ok(transpiled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+    use crate::equiv::{self, Verdict};
+
+    #[test]
+    fn equivalent() {
+        let circuit = circuit(
+            "qreg q[3];
+            gate foo(a) x, y { ry(a) x; crx(a) y, x; }
+            h q; foo(0.3) q[2], q[0]; u3(1, 2, 3) q[1]; ccx q[0], q[1], q[2]; cswap q[2], q[0], q[1];
+            rxx(0.7) q[0], q[1]; cryy(0.4) q[2], q[1], q[0]; i_swap q[0], q[2];
+            csqrt_swap q[1], q[0], q[2]; sqrt_i_swap q[1], q[2]; cu1(0.5) q[0], q[1]; ct q[1], q[2]; qft q;",
+        );
+
+        for basis in ["cx,rz,sx", "cx,u3", "cz,rz,ry", "cx,rz,rx"] {
+            let basis: Basis = basis.parse().unwrap();
+            let transpiled = basis.transpile(&circuit).unwrap();
+            assert!(transpiled.ops.iter().all(|op| basis.has(&op.name())));
+            assert_eq!(
+                equiv::check(&circuit, &transpiled).unwrap().verdict,
+                Verdict::Equivalent,
+                "basis {basis}"
+            );
+        }
+    }
+
+    #[test]
+    fn qasm() {
+        let basis: Basis = DEFAULT_BASIS.parse().unwrap();
+        let transpiled = basis
+            .transpile(&circuit(
+                "qreg q[2]; creg c[2]; h q[0]; cx q[0], q[1]; measure q -> c;",
+            ))
+            .unwrap();
+
+        assert_eq!(
+            transpiled.to_qasm().unwrap(),
+            "OPENQASM 2.0;
+gate sx a { h a; s a; h a; }
+qreg q[2];
+creg c[2];
+rz(pi/2) q[0];
+sx q[0];
+rz(pi/2) q[0];
+cx q[0], q[1];
+measure q -> c;
+"
+        );
+        assert_eq!(
+            "rz,sx"
+                .parse::<Basis>()
+                .unwrap()
+                .transpile(&circuit("qreg q[2]; cz q[0], q[1];")),
+            Err(Error::NoEntangler("rz,sx".to_string()))
+        );
+        assert_eq!(
+            "cx,foo".parse::<Basis>(),
+            Err(Error::UnsupportedGate("foo".to_string()))
+        );
+    }
+}
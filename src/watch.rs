@@ -0,0 +1,259 @@
+use std::{fmt, str::FromStr};
+
+use num_complex::Complex64 as C;
+
+use crate::{
+    circuit::{self, Circuit},
+    observable::{self, Observable},
+    sim::Sim,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidExpr(String),
+    Observable(observable::Error),
+}
+
+impl From<Error> for crate::lines::Error {
+    fn from(e: Error) -> Self {
+        Self::Watch(e)
+    }
+}
+
+impl From<observable::Error> for Error {
+    fn from(e: observable::Error) -> Self {
+        Self::Observable(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidExpr(expr) => write!(
+                f,
+                "Invalid watch expression {expr:?}: expected `prob QUBIT` or `expect PAULIS`"
+            ),
+            Error::Observable(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Expression, which is evaluated after each operation of the traced run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Watch {
+    /// Probability of all given qubits (e.g. `q[0]` or the whole register `q`) to be in `|1>`.
+    Prob(String),
+    /// Expectation value of Pauli observable, e.g. `Z0 Z1`, where numbers are global qubit indices.
+    Expect(Observable),
+}
+
+impl FromStr for Watch {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidExpr(source.to_string());
+        let (kind, arg) = source.trim().split_once(' ').ok_or_else(invalid)?;
+        match (kind, arg.trim()) {
+            ("prob", arg) if !arg.contains(char::is_whitespace) => Ok(Watch::Prob(arg.to_string())),
+            ("expect", arg) => Ok(Watch::Expect(arg.parse()?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watch::Prob(arg) => write!(f, "prob {arg}"),
+            Watch::Expect(obs) => write!(f, "expect {obs}"),
+        }
+    }
+}
+
+/// Watch expression, resolved against the circuit registers.
+enum Probe {
+    Prob(usize),
+    Expect(Observable),
+}
+
+impl Probe {
+    fn eval(&self, psi: &[C]) -> f64 {
+        match self {
+            Probe::Prob(mask) => psi
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| idx & mask == *mask)
+                .map(|(_, amp)| amp.norm_sqr())
+                .sum(),
+            Probe::Expect(obs) => obs.expectation(psi),
+        }
+    }
+}
+
+impl Watch {
+    /// Evaluates the expression in the state `psi` of the circuit.
+    pub fn value(&self, circuit: &Circuit, psi: &[C]) -> circuit::Result<f64> {
+        Ok(self.probe(circuit)?.eval(psi))
+    }
+
+    fn probe(&self, circuit: &Circuit) -> circuit::Result<Probe> {
+        match self {
+            Watch::Prob(arg) => circuit.q_mask_of(arg).map(Probe::Prob),
+            Watch::Expect(obs) => match circuit::bits(obs.q_mask()).last() {
+                Some(bit) if bit >= circuit.q_num() => {
+                    Err(circuit::Error::QubitOutOfRange(bit, circuit.q_num()))
+                }
+                _ => Ok(Probe::Expect(obs.clone())),
+            },
+        }
+    }
+}
+
+/// Values of watch expressions over operations of the circuit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    headers: Vec<String>,
+    /// Executed operation (none for the initial state) and values after it.
+    rows: Vec<(String, Vec<f64>)>,
+}
+
+/// Runs the circuit with macros expanded operation by operation,
+/// evaluating `watches` in the initial state and after each operation.
+///
+/// Simulation must be initialised with the circuit beforehand.
+pub fn trace(circuit: &Circuit, sim: &mut Sim, watches: &[Watch]) -> circuit::Result<Trace> {
+    let probes = watches
+        .iter()
+        .map(|watch| watch.probe(circuit))
+        .collect::<circuit::Result<Vec<_>>>()?;
+    let values =
+        |sim: &Sim| -> Vec<f64> { probes.iter().map(|probe| probe.eval(sim.state())).collect() };
+
+    sim.reset();
+    let circuit = circuit.flatten();
+    let mut rows = vec![(String::new(), values(sim))];
+    for op in &circuit.ops {
+        sim.step(op);
+        let source = circuit.op_to_qasm(op).unwrap_or_else(|_| op.name());
+        rows.push((source.trim_end_matches(';').to_string(), values(sim)));
+    }
+
+    Ok(Trace {
+        headers: watches.iter().map(Watch::to_string).collect(),
+        rows,
+    })
+}
+
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+impl Trace {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("op,operation");
+        for header in &self.headers {
+            csv += &format!(",{}", csv_field(header));
+        }
+        csv.push('\n');
+        for (idx, (op, values)) in self.rows.iter().enumerate() {
+            csv += &format!("{idx},{}", csv_field(op));
+            for value in values {
+                csv += &format!(",{value}");
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let idx_width = (self.rows.len() - 1).to_string().len().max(2);
+        let op_width = self
+            .rows
+            .iter()
+            .map(|(op, _)| op.len())
+            .chain([9])
+            .max()
+            .unwrap_or(0);
+
+        write!(f, "{:>idx_width$}  {:<op_width$}", "op", "operation")?;
+        for header in &self.headers {
+            write!(f, "  {header:>9}")?;
+        }
+        writeln!(f)?;
+        for (idx, (op, values)) in self.rows.iter().enumerate() {
+            let op = match idx {
+                0 => "(initial)",
+                _ => op.as_str(),
+            };
+            write!(f, "{idx:>idx_width$}  {op:<op_width$}")?;
+            for (header, value) in self.headers.iter().zip(values) {
+                let width = header.len().max(9);
+                write!(f, "  {value:>width$.4}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::circuit;
+
+    #[test]
+    fn parse() {
+        assert_eq!("prob q[0]".parse(), Ok(Watch::Prob("q[0]".to_string())));
+        assert_eq!(
+            "expect Z0 Z1"
+                .parse::<Watch>()
+                .map(|watch| watch.to_string()),
+            Ok("expect Z0 Z1".to_string())
+        );
+        assert_eq!(
+            "prob".parse::<Watch>(),
+            Err(Error::InvalidExpr("prob".to_string()))
+        );
+    }
+
+    #[test]
+    fn bell() {
+        let source = "qreg q[2]; h q[0]; cx q[0], q[1]; x q[1];";
+        let circuit = circuit(source);
+        let mut sim = Sim::default();
+        sim.init(circuit.clone()).unwrap();
+        let watches =
+            ["prob q[1]", "expect Z0 Z1", "prob q"].map(|watch| watch.parse::<Watch>().unwrap());
+        let trace = trace(&circuit, &mut sim, &watches).unwrap();
+
+        let expected = [
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [0.5, 1.0, 0.5],
+            [0.5, -1.0, 0.0],
+        ];
+        assert_eq!(trace.rows.len(), expected.len());
+        for ((_, values), expected) in trace.rows.iter().zip(expected) {
+            for (value, expected) in values.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-12);
+            }
+        }
+        let csv = trace.to_csv();
+        assert_eq!(
+            csv.lines().next(),
+            Some("op,operation,prob q[1],expect Z0 Z1,prob q")
+        );
+        assert!(csv
+            .lines()
+            .nth(3)
+            .unwrap()
+            .starts_with("2,\"cx q[0], q[1]\",0.5"));
+    }
+}